@@ -0,0 +1,84 @@
+//! Compares serializing borrowed and owned optional strings, and counts the
+//! allocations per serialized struct.
+//!
+//! `Option<&str>`, `Option<Cow<str>>` and `Option<String>` all serialize via
+//! `serialize_str`, so they are expected to cost the same. Values which need
+//! no percent-encoding are written without being copied.
+//!
+//! Run with `cargo bench --bench option`. Without `--bench`, e.g. as part of
+//! `cargo test --all-targets`, only a short smoke run is done.
+
+use serde::Serialize;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[derive(Serialize)]
+struct Params<T> {
+    a: Option<T>,
+    b: Option<T>,
+    c: Option<T>,
+}
+
+fn run<T: Serialize>(name: &str, params: &Params<T>, rounds: usize) {
+    let mut buf = Vec::with_capacity(128);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..rounds {
+        buf.clear();
+        serde_url_params::to_writer(&mut buf, params).unwrap();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{:<24} {:.1} ns, {:.1} allocations per struct",
+        name,
+        elapsed.as_nanos() as f64 / rounds as f64,
+        allocations as f64 / rounds as f64
+    );
+}
+
+fn main() {
+    let full = std::env::args().any(|arg| arg == "--bench");
+    let rounds = if full { 1_000_000 } else { 1_000 };
+    for value in ["plain-value", "needs encoding"] {
+        println!("{:?}:", value);
+        let borrowed = Params {
+            a: Some(value),
+            b: Some(value),
+            c: None,
+        };
+        run("  Option<&str>", &borrowed, rounds);
+        let cow = Params {
+            a: Some(Cow::Borrowed(value)),
+            b: Some(Cow::Borrowed(value)),
+            c: None,
+        };
+        run("  Option<Cow<str>>", &cow, rounds);
+        let owned = Params {
+            a: Some(String::from(value)),
+            b: Some(String::from(value)),
+            c: None,
+        };
+        run("  Option<String>", &owned, rounds);
+    }
+}
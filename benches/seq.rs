@@ -0,0 +1,32 @@
+//! Measures the cost per element of serializing a large sequence.
+//!
+//! Run with `cargo bench --bench seq`. Without `--bench`, e.g. as part of
+//! `cargo test --all-targets`, only a short smoke run is done.
+
+use serde::Serialize;
+use std::time::Instant;
+
+#[derive(Serialize)]
+struct Params {
+    id: Vec<u32>,
+}
+
+fn main() {
+    let full = std::env::args().any(|arg| arg == "--bench");
+    let (len, rounds) = if full { (1_000_000, 10) } else { (1_000, 1) };
+    let params = Params {
+        id: (0..len).collect(),
+    };
+    let mut buf = Vec::with_capacity(len as usize * 12);
+    let start = Instant::now();
+    for _ in 0..rounds {
+        buf.clear();
+        serde_url_params::to_writer(&mut buf, &params).unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "Vec<u32> of {} elements: {:.1} ns per element",
+        len,
+        elapsed.as_nanos() as f64 / (len as f64 * rounds as f64)
+    );
+}
@@ -0,0 +1,125 @@
+//! Compares the cost of serializing string values with the runtime `Config`
+//! against `url::form_urlencoded`, whose encoding is fixed at compile time.
+//!
+//! The difference bounds what selecting the options at compile time, e.g. by
+//! a typestate serializer, could gain on this path. With the `unstable`
+//! feature, the encoding of the values by `Config::encode_value` is compared
+//! against the prototype `StaticEncoder`, whose encode set is selected at
+//! compile time.
+//!
+//! Run with `cargo bench --bench str [--features unstable]`. Without
+//! `--bench`, e.g. as part of `cargo test --all-targets`, only a short smoke
+//! run is done.
+
+use serde::Serialize;
+use serde_url_params::{Config, EncodeSet};
+use std::time::{Duration, Instant};
+
+#[derive(Serialize)]
+struct Params {
+    q: Vec<String>,
+}
+
+fn measure(rounds: usize, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        f();
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let full = std::env::args().any(|arg| arg == "--bench");
+    let (len, rounds) = if full { (100_000, 20) } else { (100, 1) };
+    let params = Params {
+        q: (0..len).map(|i| format!("caf\u{e9} & bar {}", i)).collect(),
+    };
+    let per_value = |elapsed: Duration| elapsed.as_nanos() as f64 / (len * rounds) as f64;
+
+    let mut buf = Vec::with_capacity(len * 32);
+    let elapsed = measure(rounds, || {
+        buf.clear();
+        serde_url_params::to_writer(&mut buf, &params).unwrap();
+    });
+    println!(
+        "to_writer:              {:.1} ns per value",
+        per_value(elapsed)
+    );
+
+    let config = Config::new().encode_set(EncodeSet::Unreserved);
+    let elapsed = measure(rounds, || {
+        buf.clear();
+        config.to_writer(&mut buf, &params).unwrap();
+    });
+    println!(
+        "to_writer (Unreserved): {:.1} ns per value",
+        per_value(elapsed)
+    );
+
+    let elapsed = measure(rounds, || {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        for value in &params.q {
+            serializer.append_pair("q", value);
+        }
+        serializer.finish();
+    });
+    println!(
+        "form_urlencoded:        {:.1} ns per value",
+        per_value(elapsed)
+    );
+
+    #[cfg(feature = "unstable")]
+    {
+        use serde_url_params::StaticEncoder;
+
+        // Values needing encoding, and plain values which are only scanned.
+        let plain: Vec<String> = (0..len).map(|i| format!("value-{}", i)).collect();
+        for (values, kind) in [(&params.q, "encoded"), (&plain, "plain")] {
+            bench_encode(StaticEncoder::new(), "Form", values, kind, rounds);
+            let encoder = StaticEncoder::new().unreserved();
+            bench_encode(encoder, "Unreserved", values, kind, rounds);
+        }
+    }
+}
+
+/// Compares `Config::encode_value` against `encoder` with the same encode set.
+///
+/// The encoder is passed by type, so the timed loop is monomorphized for the
+/// encode set like a compile-time selected serializer would be.
+#[cfg(feature = "unstable")]
+fn bench_encode<S>(
+    encoder: serde_url_params::StaticEncoder<S>,
+    set: &str,
+    values: &[String],
+    kind: &str,
+    rounds: usize,
+) where
+    S: serde_url_params::StaticEncodeSet,
+{
+    use std::hint::black_box;
+
+    let per_value = |elapsed: Duration| elapsed.as_nanos() as f64 / (values.len() * rounds) as f64;
+    let config = Config::new().encode_set(S::SET);
+    let elapsed = measure(rounds, || {
+        for value in values {
+            black_box(config.encode_value(black_box(value)));
+        }
+    });
+    println!(
+        "Config::encode_value ({}, {}): {:.1} ns per value",
+        set,
+        kind,
+        per_value(elapsed)
+    );
+    let elapsed = measure(rounds, || {
+        for value in values {
+            black_box(encoder.encode(black_box(value)));
+        }
+    });
+    println!(
+        "StaticEncoder::encode ({}, {}): {:.1} ns per value",
+        set,
+        kind,
+        per_value(elapsed)
+    );
+}
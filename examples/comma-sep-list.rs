@@ -1,8 +1,13 @@
 //! Example how to serialize a list of parameter values as a comma-separated list (non-exploded)
+//!
+//! The list is joined by hand, so the commas are encoded together with the
+//! values. Alternatively, `ArrayFormat::Comma` joins any sequence, writing the
+//! commas literally unless `Config::comma_literal(false)` is set.
 use core::fmt;
 
 use itertools::Itertools;
 use serde::{Serialize, Serializer};
+use serde_url_params::{ArrayFormat, Config};
 
 #[derive(Serialize)]
 struct AuthorizationParameters<'a> {
@@ -27,4 +32,17 @@ fn main() {
     };
     let expected = "scope=openid%2Cprofile";
     assert_eq!(serde_url_params::to_string(&params).unwrap(), expected);
+
+    #[derive(Serialize)]
+    struct Scopes<'a> {
+        scope: Vec<&'a str>,
+    }
+
+    let params = Scopes {
+        scope: vec!["openid", "profile"],
+    };
+    let config = Config::new().array_format(ArrayFormat::Comma);
+    assert_eq!(config.to_string(&params).unwrap(), "scope=openid,profile");
+    let config = config.comma_literal(false);
+    assert_eq!(config.to_string(&params).unwrap(), expected);
 }
@@ -0,0 +1,1707 @@
+//! Options controlling how values are serialized into URL parameters.
+
+use crate::error::Result;
+use crate::ser::{Explanation, Serializer, Warning};
+use percent_encoding::percent_encode_byte;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// How boolean values are written.
+///
+/// `Option<bool>` can be used as a tri-state value: `Some` is written in the
+/// chosen format and `None` is omitted. This only works with the formats which
+/// write `false` though, since [`BoolFormat::FlagIfTrue`] and
+/// [`BoolFormat::OmitIfFalse`] omit `Some(false)` just like `None`.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::{BoolFormat, Config};
+///
+/// #[derive(Serialize)]
+/// struct Update {
+///     enabled: Option<bool>,
+/// }
+///
+/// let config = Config::new().bool_format(BoolFormat::OnOff);
+/// let update = |enabled| config.to_string(&Update { enabled }).unwrap();
+/// assert_eq!(update(Some(true)), "enabled=on");
+/// assert_eq!(update(Some(false)), "enabled=off");
+/// assert_eq!(update(None), "");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoolFormat {
+    /// `key=true` resp. `key=false` (default).
+    #[default]
+    TrueFalse,
+    /// `key=1` resp. `key=0`.
+    Numeric,
+    /// `key=yes` resp. `key=no`.
+    YesNo,
+    /// `key=on` resp. `key=off`.
+    OnOff,
+    /// Presence-only flag: `key` (without a value) when true, nothing when
+    /// false.
+    FlagIfTrue,
+    /// `key=true` when true, nothing when false.
+    OmitIfFalse,
+}
+
+/// How unit values, i.e. `()` and unit structs, are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitFormat {
+    /// Nothing is written (default).
+    #[default]
+    Omit,
+    /// The key is written without a value: `key`.
+    Flag,
+    /// The key is written with an empty value: `key=`.
+    EmptyValue,
+}
+
+/// How unit variants of enums are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitVariantFormat {
+    /// The variant name is the value: `key=Variant` (default).
+    #[default]
+    Name,
+    /// The variant name replaces the key, with `1` as value: `Variant=1`.
+    KeyWithOne,
+    /// The index of the variant in declaration order is the value: `key=0`
+    /// for the first variant. The index starts at
+    /// [`Config::unit_variant_index_base`]; explicit discriminants are
+    /// ignored.
+    Index,
+}
+
+/// How newtype variants, e.g. `Ok(T)` and `Err(E)` of `Result`, are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NewtypeVariantFormat {
+    /// Only the inner value, the variant name is omitted: `key=value`
+    /// (default).
+    #[default]
+    Value,
+    /// The inner value prefixed by the variant name: `key=Variant:value`. The
+    /// separator can be changed by [`Config::variant_separator`].
+    TaggedColon,
+}
+
+/// How line breaks in string values are normalized before encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NewlinePolicy {
+    /// Line breaks are kept as they are (default).
+    #[default]
+    AsIs,
+    /// `\r\n` and a lone `\r` become `\n`, encoded as `%0A`.
+    Lf,
+    /// `\n` and a lone `\r` become `\r\n`, encoded as `%0D%0A`.
+    CrLf,
+}
+
+impl NewlinePolicy {
+    /// Normalizes the line breaks of the value.
+    pub(crate) fn normalize(self, value: &str) -> Cow<'_, str> {
+        if self == NewlinePolicy::AsIs || !value.contains(['\r', '\n']) {
+            return Cow::Borrowed(value);
+        }
+        let lf = value.replace("\r\n", "\n").replace('\r', "\n");
+        match self {
+            NewlinePolicy::CrLf => Cow::Owned(lf.replace('\n', "\r\n")),
+            _ => Cow::Owned(lf),
+        }
+    }
+}
+
+/// Which bytes of keys and string values are percent-encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EncodeSet {
+    /// `application/x-www-form-urlencoded`: all bytes except ASCII
+    /// alphanumerics and `*-._` are encoded, and a space is written as `+`
+    /// (default).
+    #[default]
+    Form,
+    /// RFC 3986: all bytes except the unreserved characters, i.e. ASCII
+    /// alphanumerics and `-._~`, are encoded, and a space is written as
+    /// `%20`. This is required by signature algorithms like OAuth 1.0 or AWS
+    /// Signature Version 4, which sign canonical query strings.
+    Unreserved,
+}
+
+impl EncodeSet {
+    /// The ASCII bytes which are written literally.
+    const fn literal(self) -> ByteSet {
+        let set = ByteSet::ALPHANUMERIC.with(b"-._");
+        match self {
+            EncodeSet::Form => set.with(b"*"),
+            EncodeSet::Unreserved => set.with(b"~"),
+        }
+    }
+}
+
+/// Set of ASCII bytes as bit mask, indexed by byte value.
+///
+/// Unlike `percent_encoding::AsciiSet`, the set can be composed at runtime
+/// and queried.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct ByteSet(u128);
+
+impl ByteSet {
+    const ALPHANUMERIC: ByteSet = ByteSet(0x07ff_fffe_07ff_fffe_03ff_0000_0000_0000);
+
+    /// Adds the given bytes; non-ASCII bytes are ignored.
+    const fn with(self, bytes: &[u8]) -> Self {
+        let mut mask = self.0;
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i].is_ascii() {
+                mask |= 1 << bytes[i];
+            }
+            i += 1;
+        }
+        ByteSet(mask)
+    }
+
+    /// Removes the given bytes.
+    fn without(self, bytes: &[u8]) -> Self {
+        self.difference(ByteSet::default().with(bytes))
+    }
+
+    fn union(self, other: ByteSet) -> Self {
+        ByteSet(self.0 | other.0)
+    }
+
+    fn difference(self, other: ByteSet) -> Self {
+        ByteSet(self.0 & !other.0)
+    }
+
+    fn contains(self, byte: u8) -> bool {
+        byte.is_ascii() && self.0 >> byte & 1 == 1
+    }
+}
+
+/// Percent-encodes all bytes of `value` except `literal`, writing a space as
+/// `+` if `plus` is set, and non-ASCII characters as they are if
+/// `utf8_passthrough` is set. The value is borrowed if nothing needs to be
+/// encoded.
+#[inline]
+fn percent_encode(
+    value: &str,
+    literal: ByteSet,
+    plus: bool,
+    utf8_passthrough: bool,
+) -> Cow<'_, str> {
+    if value.bytes().all(|byte| literal.contains(byte)) {
+        return Cow::Borrowed(value);
+    }
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        if !c.is_ascii() {
+            if utf8_passthrough {
+                encoded.push(c);
+            } else {
+                for &byte in c.encode_utf8(&mut [0; 4]).as_bytes() {
+                    encoded.push_str(percent_encode_byte(byte));
+                }
+            }
+            continue;
+        }
+        let byte = c as u8;
+        if literal.contains(byte) {
+            encoded.push(char::from(byte));
+        } else if byte == b' ' && plus {
+            encoded.push('+');
+        } else {
+            encoded.push_str(percent_encode_byte(byte));
+        }
+    }
+    Cow::Owned(encoded)
+}
+
+/// Encode set selected at compile time by a marker type, see
+/// [`StaticEncoder`].
+#[cfg(feature = "unstable")]
+pub trait StaticEncodeSet {
+    /// The encode set the marker type stands for.
+    const SET: EncodeSet;
+}
+
+/// Marker type of [`EncodeSet::Form`].
+#[cfg(feature = "unstable")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Form;
+
+#[cfg(feature = "unstable")]
+impl StaticEncodeSet for Form {
+    const SET: EncodeSet = EncodeSet::Form;
+}
+
+/// Marker type of [`EncodeSet::Unreserved`].
+#[cfg(feature = "unstable")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Unreserved;
+
+#[cfg(feature = "unstable")]
+impl StaticEncodeSet for Unreserved {
+    const SET: EncodeSet = EncodeSet::Unreserved;
+}
+
+/// Prototype of a string encoder whose encode set is selected at compile
+/// time, by its type, instead of by a runtime [`Config`].
+///
+/// The encoder is chosen by a typestate builder. It encodes exactly like
+/// [`Config::encode_value`] with the same encode set and no other options,
+/// but the literal bytes and the space handling are constants of the
+/// monomorphized code. This is only available with the `unstable` feature,
+/// and serves to benchmark compile-time selected options against the
+/// runtime configuration.
+///
+/// ```rust
+/// use serde_url_params::StaticEncoder;
+///
+/// let encoder = StaticEncoder::new();
+/// assert_eq!(encoder.encode("a b~"), "a+b%7E");
+/// let encoder = encoder.unreserved();
+/// assert_eq!(encoder.encode("a b~"), "a%20b~");
+/// ```
+#[cfg(feature = "unstable")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StaticEncoder<S = Form>(std::marker::PhantomData<S>);
+
+#[cfg(feature = "unstable")]
+impl StaticEncoder {
+    /// Creates an encoder with [`EncodeSet::Form`].
+    pub fn new() -> Self {
+        StaticEncoder(std::marker::PhantomData)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl<S: StaticEncodeSet> StaticEncoder<S> {
+    const LITERAL: ByteSet = S::SET.literal();
+    const PLUS: bool = matches!(S::SET, EncodeSet::Form);
+
+    /// Switches to [`EncodeSet::Form`].
+    pub fn form(self) -> StaticEncoder<Form> {
+        StaticEncoder(std::marker::PhantomData)
+    }
+
+    /// Switches to [`EncodeSet::Unreserved`].
+    pub fn unreserved(self) -> StaticEncoder<Unreserved> {
+        StaticEncoder(std::marker::PhantomData)
+    }
+
+    /// Percent-encodes the string value. The value is borrowed if nothing
+    /// needs to be encoded.
+    pub fn encode<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        percent_encode(value, Self::LITERAL, Self::PLUS, false)
+    }
+}
+
+/// How byte strings are written.
+///
+/// This applies to values serialized via `serialize_bytes`, e.g. by the
+/// [`bytes`](../helpers/fn.bytes.html) helper or by `serde_bytes`. Note that
+/// `Vec<u8>` and `&[u8]` are sequences of integers by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BytesFormat {
+    /// Each byte is an element of a sequence, written according to the
+    /// [`ArrayFormat`]: `key=1&key=2` (default).
+    #[default]
+    Seq,
+    /// A single parameter with the bytes encoded as standard Base64 with
+    /// padding, percent-encoded afterwards: `key=AQI%3D`.
+    Base64,
+    /// A single parameter with the bytes encoded as lowercase hexadecimal:
+    /// `key=0102`.
+    Hex,
+}
+
+/// How elements of sequences, e.g. `Vec`s and tuples, are written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayFormat {
+    /// Each element is written as a separate parameter with the same key:
+    /// `key=a&key=b` (default).
+    #[default]
+    Repeated,
+    /// Each element is written as a separate parameter whose key is suffixed
+    /// by the element's index: `key0=a&key1=b`.
+    NumberedSuffix,
+    /// Each element is written as a separate parameter whose key is suffixed
+    /// by the element's index in brackets: `key[0]=a&key[1]=b`. Nested
+    /// sequences get one index per level, e.g. `m[0][1]=b` for a matrix.
+    Indexed,
+    /// All elements are joined by commas into a single parameter:
+    /// `key=a,b`. The elements are encoded separately, so a comma within an
+    /// element is encoded as `%2C`. An empty sequence is omitted.
+    Comma,
+    /// Each element is written as a separate parameter whose key is suffixed
+    /// by brackets, by default empty ones: `key[]=a&key[]=b`. See
+    /// [`Config::bracket_content`].
+    Brackets,
+}
+
+/// What is written between the brackets of [`ArrayFormat::Brackets`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum BracketContent {
+    /// Nothing: `key[]=a&key[]=b` (default).
+    #[default]
+    Empty,
+    /// The element's index: `key[0]=a&key[1]=b`.
+    Index,
+    /// The given literal: `key[x]=a&key[x]=b`.
+    Custom(String),
+}
+
+/// What happens when different struct fields or map entries emit the same
+/// key.
+///
+/// This typically happens when a map flattened into a struct via
+/// `#[serde(flatten)]` contains a key which is also a field of the struct.
+/// Since the struct is serialized as a single map then, the serializer does not
+/// know whether a key stems from a field or from the flattened map. Hence, the
+/// policies are defined by serialization order, which is the declaration order
+/// of the fields: for a flattened map declared after a field, `FirstWins`
+/// keeps the field and `LastWins` keeps the map entry.
+///
+/// Repeated keys of the elements of a single sequence are never a collision.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyCollision {
+    /// All parameters are written, so the key occurs several times (default).
+    #[default]
+    Allow,
+    /// Serialization fails with a custom error.
+    Error,
+    /// The parameters of the first field or entry emitting the key are kept.
+    FirstWins,
+    /// The parameters of the last field or entry emitting the key are kept.
+    ///
+    /// This requires serializing the value twice: once to find out which
+    /// keys are emitted, and once to write the output.
+    LastWins,
+}
+
+/// How a scalar value outside of any struct or map is handled.
+///
+/// A scalar, like a number or a string, has no key to be written with, so it
+/// cannot be serialized on its own. The same holds for the elements of a top
+/// level sequence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TopLevelPolicy {
+    /// Serialization fails with a custom error (default).
+    #[default]
+    Error,
+    /// The value is dropped, so the output stays empty.
+    Empty,
+}
+
+/// How an output without any parameter is handled, e.g. of a struct whose
+/// fields are all `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EmptyOutputPolicy {
+    /// The empty output is returned (default).
+    #[default]
+    Ok,
+    /// Serialization fails with a custom error, so callers can detect that
+    /// there is nothing to send.
+    Error,
+}
+
+/// Order in which the parameters of struct fields and map entries are
+/// written.
+///
+/// The elements of a sequence always keep their order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Order {
+    /// In serialization order, i.e. declaration order of struct fields and
+    /// iteration order of maps (default). The parameters are streamed.
+    #[default]
+    Declaration,
+    /// In reverse serialization order. The entries are buffered until the
+    /// end of each struct resp. map.
+    Reverse,
+    /// Sorted by key, like [`Config::sort_map_keys`] but for structs as well.
+    /// The entries are buffered until the end of each struct resp. map.
+    Sorted,
+}
+
+/// Case convention of the keys written for struct fields.
+///
+/// Field names are expected in `snake_case`, as Rust fields are usually
+/// named, and are split into words at underscores. This corresponds to
+/// `#[serde(rename_all = "...")]`, applied to the fields of all structs and
+/// struct variants at once. Fields renamed by serde are converted as well.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyCase {
+    /// The field name is written unchanged (default).
+    #[default]
+    AsIs,
+    /// `userName`
+    Camel,
+    /// `UserName`
+    Pascal,
+    /// `user-name`
+    Kebab,
+    /// `USER_NAME`
+    ScreamingSnake,
+}
+
+impl KeyCase {
+    /// Converts the field name to this case.
+    pub(crate) fn apply(self, key: &str) -> Cow<'_, str> {
+        let mut words = key.split('_').filter(|word| !word.is_empty());
+        match self {
+            KeyCase::AsIs => Cow::Borrowed(key),
+            KeyCase::Camel | KeyCase::Pascal => {
+                let mut converted = String::with_capacity(key.len());
+                if self == KeyCase::Camel {
+                    converted.extend(words.next());
+                }
+                for word in words {
+                    let mut chars = word.chars();
+                    converted.extend(chars.next().map(|c| c.to_ascii_uppercase()));
+                    converted.extend(chars);
+                }
+                Cow::Owned(converted)
+            }
+            KeyCase::Kebab => Cow::Owned(words.collect::<Vec<_>>().join("-")),
+            KeyCase::ScreamingSnake => Cow::Owned(key.to_ascii_uppercase()),
+        }
+    }
+}
+
+type Callback = dyn FnMut(&str, &str) + Send;
+
+/// Callback observing each emitted key value pair.
+#[derive(Clone)]
+pub(crate) struct PairCallback(Arc<Mutex<Callback>>);
+
+impl PairCallback {
+    pub(crate) fn call(&self, key: &str, value: &str) {
+        // A callback which panicked before is still called, since a poisoned
+        // lock must not silently drop the notifications of later pairs.
+        let mut callback = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        callback(key, value);
+    }
+}
+
+impl fmt::Debug for PairCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PairCallback")
+    }
+}
+
+type Mapper = dyn Fn(&str) -> Cow<'_, str> + Send + Sync;
+
+/// Callback renaming each emitted key.
+#[derive(Clone)]
+pub(crate) struct KeyMapper(Arc<Mapper>);
+
+impl fmt::Debug for KeyMapper {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("KeyMapper")
+    }
+}
+
+/// Configuration of the URL parameters serializer.
+///
+/// The default configuration produces the same output as
+/// [`to_string`](../ser/fn.to_string.html) and friends. Options are set via
+/// builder methods, and a configuration is applied either by its `to_*`
+/// methods or by [`Serializer::new_with_config`]:
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::{BoolFormat, Config};
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+///     include_deleted: bool,
+/// }
+///
+/// let config = Config::new().bool_format(BoolFormat::FlagIfTrue);
+/// let search = Search {
+///     q: "rust",
+///     include_deleted: true,
+/// };
+/// assert_eq!(config.to_string(&search).unwrap(), "q=rust&include_deleted");
+/// ```
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct Config {
+    pub(crate) bool_format: BoolFormat,
+    pub(crate) array_format: ArrayFormat,
+    pub(crate) index_base: usize,
+    pub(crate) bracket_content: BracketContent,
+    pub(crate) unit_format: UnitFormat,
+    pub(crate) none_sentinel: Option<String>,
+    pub(crate) unit_variant_format: UnitVariantFormat,
+    pub(crate) unit_variant_index_base: u32,
+    pub(crate) skip_variants: Vec<String>,
+    pub(crate) newtype_variant_format: NewtypeVariantFormat,
+    pub(crate) variant_separator: Option<String>,
+    pub(crate) bytes_format: BytesFormat,
+    pub(crate) encode_set: EncodeSet,
+    pub(crate) encode_extra: ByteSet,
+    pub(crate) encode_skip: ByteSet,
+    pub(crate) utf8_passthrough: bool,
+    pub(crate) raw_keys: bool,
+    pub(crate) raw_values: bool,
+    pub(crate) extra_params: Vec<(String, String)>,
+    pub(crate) skip_zero_numbers: bool,
+    pub(crate) trim_values: bool,
+    pub(crate) newline_normalization: NewlinePolicy,
+    pub(crate) max_seq_len: Option<usize>,
+    pub(crate) max_key_len: Option<usize>,
+    pub(crate) strict: bool,
+    pub(crate) strict_units: bool,
+    pub(crate) sort_seq_values: bool,
+    pub(crate) comma_dedup: bool,
+    pub(crate) comma_encoded: bool,
+    pub(crate) sort_map_keys: bool,
+    pub(crate) order: Order,
+    pub(crate) key_case: KeyCase,
+    pub(crate) key_collision: KeyCollision,
+    pub(crate) coalesce_duplicate_keys: bool,
+    pub(crate) trailing_separator: bool,
+    pub(crate) top_level_scalar: TopLevelPolicy,
+    pub(crate) empty_output: EmptyOutputPolicy,
+    pub(crate) on_pair: Option<PairCallback>,
+    pub(crate) key_mapper: Option<KeyMapper>,
+}
+
+impl Config {
+    /// Creates a new default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how boolean values are written.
+    pub fn bool_format(mut self, format: BoolFormat) -> Self {
+        self.bool_format = format;
+        self
+    }
+
+    /// Sets how elements of sequences are written.
+    pub fn array_format(mut self, format: ArrayFormat) -> Self {
+        self.array_format = format;
+        self
+    }
+
+    /// Sets the index of the first element of a sequence, for the array
+    /// formats writing indices (default 0).
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{ArrayFormat, Config};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     filter: Vec<&'static str>,
+    /// }
+    ///
+    /// let params = Params {
+    ///     filter: vec!["a", "b"],
+    /// };
+    /// let config = Config::new()
+    ///     .array_format(ArrayFormat::Indexed)
+    ///     .index_base(1);
+    /// assert_eq!(config.to_string(&params).unwrap(), "filter[1]=a&filter[2]=b");
+    /// ```
+    pub fn index_base(mut self, base: usize) -> Self {
+        self.index_base = base;
+        self
+    }
+
+    /// Sets what is written between the brackets of
+    /// [`ArrayFormat::Brackets`].
+    ///
+    /// Frameworks like PHP and Rails expect empty brackets, others an index.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{ArrayFormat, BracketContent, Config};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     ids: Vec<u32>,
+    /// }
+    ///
+    /// let params = Params { ids: vec![1, 2] };
+    /// let config = Config::new().array_format(ArrayFormat::Brackets);
+    /// assert_eq!(config.to_string(&params).unwrap(), "ids[]=1&ids[]=2");
+    /// let config = config.bracket_content(BracketContent::Index);
+    /// assert_eq!(config.to_string(&params).unwrap(), "ids[0]=1&ids[1]=2");
+    /// ```
+    pub fn bracket_content(mut self, content: BracketContent) -> Self {
+        self.bracket_content = content;
+        self
+    }
+
+    /// Sets how unit values are written.
+    ///
+    /// This allows to distinguish a bare `key` from `key=`, which some APIs
+    /// treat differently. Note that `PhantomData` is serialized as a unit
+    /// struct as well.
+    pub fn unit_format(mut self, format: UnitFormat) -> Self {
+        self.unit_format = format;
+        self
+    }
+
+    /// Sets a sentinel which is written as the value of `None`.
+    ///
+    /// By default (`None`), a field set to `None` is omitted. With a
+    /// sentinel, the key is always written, e.g. for APIs requiring every
+    /// field to be present. The sentinel is percent-encoded like any other
+    /// string value. To write a sentinel for single fields only, use
+    /// [`NullIfNone`](../helpers/struct.NullIfNone.html) instead.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: &'static str,
+    ///     page: Option<u32>,
+    /// }
+    ///
+    /// let params = Params { q: "rust", page: None };
+    /// let config = Config::new().none_sentinel(Some(String::from("null")));
+    /// assert_eq!(config.to_string(&params).unwrap(), "q=rust&page=null");
+    /// let config = Config::new().none_sentinel(Some(String::new()));
+    /// assert_eq!(config.to_string(&params).unwrap(), "q=rust&page=");
+    /// ```
+    pub fn none_sentinel(mut self, sentinel: Option<String>) -> Self {
+        self.none_sentinel = sentinel;
+        self
+    }
+
+    /// Sets how unit variants of enums are written.
+    ///
+    /// With [`UnitVariantFormat::KeyWithOne`], the selected variant is written
+    /// as key with value `1`, as checkbox-style APIs expect. The field name is
+    /// not written then, so each element of a sequence of variants becomes a
+    /// parameter of its own, whatever the [`ArrayFormat`]. Unit variants used
+    /// as map keys are not affected.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, UnitVariantFormat};
+    ///
+    /// #[derive(Serialize)]
+    /// enum Selection {
+    ///     A,
+    ///     B,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Form {
+    ///     select: Selection,
+    ///     extra: Vec<Selection>,
+    /// }
+    ///
+    /// let form = Form {
+    ///     select: Selection::A,
+    ///     extra: vec![Selection::B],
+    /// };
+    /// let config = Config::new().unit_variant_format(UnitVariantFormat::KeyWithOne);
+    /// assert_eq!(config.to_string(&form).unwrap(), "A=1&B=1");
+    /// ```
+    pub fn unit_variant_format(mut self, format: UnitVariantFormat) -> Self {
+        self.unit_variant_format = format;
+        self
+    }
+
+    /// Sets the index of the first variant for [`UnitVariantFormat::Index`],
+    /// by default 0.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, UnitVariantFormat};
+    ///
+    /// #[derive(Serialize)]
+    /// enum Priority {
+    ///     Low,
+    ///     High,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     priority: Priority,
+    /// }
+    ///
+    /// let params = Params { priority: Priority::High };
+    /// let config = Config::new().unit_variant_format(UnitVariantFormat::Index);
+    /// assert_eq!(config.to_string(&params).unwrap(), "priority=1");
+    /// let config = config.unit_variant_index_base(1);
+    /// assert_eq!(config.to_string(&params).unwrap(), "priority=2");
+    /// ```
+    pub fn unit_variant_index_base(mut self, base: u32) -> Self {
+        self.unit_variant_index_base = base;
+        self
+    }
+
+    /// Sets how newtype variants are written.
+    ///
+    /// By default, the variant name is omitted, so e.g. `Ok("pass")` and
+    /// `Err("pass")` both serialize to `key=pass`. With
+    /// [`NewtypeVariantFormat::TaggedColon`] they become `key=Ok:pass` resp.
+    /// `key=Err:pass`.
+    pub fn newtype_variant_format(mut self, format: NewtypeVariantFormat) -> Self {
+        self.newtype_variant_format = format;
+        self
+    }
+
+    /// Sets the separator between variant name and value of
+    /// [`NewtypeVariantFormat::TaggedColon`], by default `:`.
+    ///
+    /// Unlike the variant name and the value, the separator is written
+    /// literally, so it must not contain characters like `&`, `=` or `#`.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, NewtypeVariantFormat};
+    ///
+    /// #[derive(Serialize)]
+    /// enum Sort {
+    ///     Asc(&'static str),
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     sort: Sort,
+    /// }
+    ///
+    /// let params = Params {
+    ///     sort: Sort::Asc("name"),
+    /// };
+    /// let config = Config::new().newtype_variant_format(NewtypeVariantFormat::TaggedColon);
+    /// assert_eq!(config.to_string(&params).unwrap(), "sort=Asc:name");
+    /// let config = config.variant_separator(".");
+    /// assert_eq!(config.to_string(&params).unwrap(), "sort=Asc.name");
+    /// ```
+    pub fn variant_separator(mut self, separator: &str) -> Self {
+        self.variant_separator = Some(String::from(separator));
+        self
+    }
+
+    /// Sets which bytes of keys and string values are percent-encoded.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, EncodeSet};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: &'static str,
+    /// }
+    ///
+    /// let params = Params { q: "a b~*" };
+    /// assert_eq!(Config::new().to_string(&params).unwrap(), "q=a+b%7E*");
+    /// let config = Config::new().encode_set(EncodeSet::Unreserved);
+    /// assert_eq!(config.to_string(&params).unwrap(), "q=a%20b~%2A");
+    /// ```
+    pub fn encode_set(mut self, set: EncodeSet) -> Self {
+        self.encode_set = set;
+        self
+    }
+
+    /// Percent-encodes the given ASCII bytes in addition to the bytes of the
+    /// [`EncodeSet`].
+    ///
+    /// E.g. with [`EncodeSet::Form`], a space is written as `%20` instead of
+    /// `+` when added. Non-ASCII bytes are always encoded, unless
+    /// [`utf8_passthrough`](Config::utf8_passthrough) is set. Calls
+    /// accumulate.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: &'static str,
+    /// }
+    ///
+    /// let params = Params { q: "a*b.c" };
+    /// let config = Config::new().encode_extra(b"*.");
+    /// assert_eq!(config.to_string(&params).unwrap(), "q=a%2Ab%2Ec");
+    /// ```
+    pub fn encode_extra(mut self, bytes: &[u8]) -> Self {
+        self.encode_extra = self.encode_extra.with(bytes);
+        self.encode_skip = self.encode_skip.without(bytes);
+        self
+    }
+
+    /// Writes the given ASCII bytes literally, although the [`EncodeSet`]
+    /// would percent-encode them.
+    ///
+    /// This overrides [`encode_extra`](Config::encode_extra) for the given
+    /// bytes, and vice versa the later call wins. Note that skipping the
+    /// encoding of `&`, `=`, `+`, `#` or `%` produces an ambiguous query.
+    /// Non-ASCII bytes are ignored. Calls accumulate.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: &'static str,
+    /// }
+    ///
+    /// let params = Params { q: "(a)!" };
+    /// let config = Config::new().encode_skip(b"()!");
+    /// assert_eq!(config.to_string(&params).unwrap(), "q=(a)!");
+    /// ```
+    pub fn encode_skip(mut self, bytes: &[u8]) -> Self {
+        self.encode_skip = self.encode_skip.with(bytes);
+        self.encode_extra = self.encode_extra.without(bytes);
+        self
+    }
+
+    /// Writes non-ASCII characters literally as UTF-8 instead of
+    /// percent-encoding their bytes.
+    ///
+    /// This is for APIs accepting raw UTF-8 in query values, e.g. to keep
+    /// URLs readable. ASCII bytes are still encoded according to the
+    /// [`EncodeSet`] and its overrides, so reserved characters stay encoded.
+    /// Note that such a query is not a valid URI per RFC 3986, and the URL
+    /// parser of a client may encode the characters again.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: &'static str,
+    /// }
+    ///
+    /// let params = Params { q: "café & crème" };
+    /// let config = Config::new().utf8_passthrough(true);
+    /// assert_eq!(config.to_string(&params).unwrap(), "q=café+%26+crème");
+    /// ```
+    pub fn utf8_passthrough(mut self, passthrough: bool) -> Self {
+        self.utf8_passthrough = passthrough;
+        self
+    }
+
+    /// Sets whether keys are percent-encoded (default) or written as they
+    /// are.
+    ///
+    /// Keys are encoded like string values, except that brackets are kept,
+    /// so that the suffixes of e.g. [`ArrayFormat::Brackets`] stay readable.
+    /// Add them to [`encode_extra`](Config::encode_extra) to encode them as
+    /// well. Writing keys unencoded is only safe if they are known not to
+    /// contain `&`, `=` or `#`.
+    ///
+    /// ```rust
+    /// use serde_url_params::Config;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut params = BTreeMap::new();
+    /// params.insert("a b", "c");
+    /// assert_eq!(Config::new().to_string(&params).unwrap(), "a+b=c");
+    /// let config = Config::new().encode_keys(false);
+    /// assert_eq!(config.to_string(&params).unwrap(), "a b=c");
+    /// ```
+    pub fn encode_keys(mut self, encode: bool) -> Self {
+        self.raw_keys = !encode;
+        self
+    }
+
+    /// Sets whether string values are percent-encoded (default) or written
+    /// as they are.
+    ///
+    /// This is for APIs whose values are guaranteed not to need encoding.
+    /// Unlike [`PreEncoded`](../helpers/struct.PreEncoded.html), it applies
+    /// to all values. Writing values unencoded is only safe if they are
+    /// known not to contain `&` or `#`.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     path: &'static str,
+    /// }
+    ///
+    /// let params = Params { path: "/a/b" };
+    /// assert_eq!(Config::new().to_string(&params).unwrap(), "path=%2Fa%2Fb");
+    /// let config = Config::new().encode_values(false);
+    /// assert_eq!(config.to_string(&params).unwrap(), "path=/a/b");
+    /// ```
+    pub fn encode_values(mut self, encode: bool) -> Self {
+        self.raw_values = !encode;
+        self
+    }
+
+    /// Percent-encodes the key according to the encode set and its
+    /// overrides, keeping brackets.
+    pub(crate) fn encode_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if self.raw_keys {
+            return Cow::Borrowed(key);
+        }
+        self.encode_with(key, self.literal(ByteSet::default().with(b"[]")))
+    }
+
+    /// Percent-encodes the value according to the encode set and its
+    /// overrides.
+    pub(crate) fn encode<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        if self.raw_values {
+            return Cow::Borrowed(value);
+        }
+        self.encode_with(value, self.literal(ByteSet::default()))
+    }
+
+    /// The ASCII bytes which are written literally, including `extra` unless
+    /// overridden by [`encode_extra`](Config::encode_extra).
+    fn literal(&self, extra: ByteSet) -> ByteSet {
+        self.encode_set
+            .literal()
+            .union(self.encode_skip)
+            .union(extra)
+            .difference(self.encode_extra)
+    }
+
+    /// Percent-encodes all bytes except `literal`. The value is borrowed if
+    /// nothing needs to be encoded.
+    fn encode_with<'a>(&self, value: &'a str, literal: ByteSet) -> Cow<'a, str> {
+        let plus = self.encode_set == EncodeSet::Form && !self.encode_extra.contains(b' ');
+        percent_encode(value, literal, plus, self.utf8_passthrough)
+    }
+
+    /// Percent-encodes a string value like the serializer does with this
+    /// configuration.
+    ///
+    /// This is only available with the `unstable` feature, e.g. for
+    /// benchmarking against [`StaticEncoder`].
+    #[cfg(feature = "unstable")]
+    pub fn encode_value<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        self.encode(value)
+    }
+
+    /// Sets how byte strings are written.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{BytesFormat, Config};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Upload<'a> {
+    ///     #[serde(serialize_with = "serde_url_params::helpers::bytes")]
+    ///     data: &'a [u8],
+    /// }
+    ///
+    /// let upload = Upload { data: b"hi!" };
+    /// assert_eq!(Config::new().to_string(&upload).unwrap(), "data=104&data=105&data=33");
+    /// let config = Config::new().bytes_format(BytesFormat::Base64);
+    /// assert_eq!(config.to_string(&upload).unwrap(), "data=aGkh");
+    /// let config = Config::new().bytes_format(BytesFormat::Hex);
+    /// assert_eq!(config.to_string(&upload).unwrap(), "data=686921");
+    /// ```
+    pub fn bytes_format(mut self, format: BytesFormat) -> Self {
+        self.bytes_format = format;
+        self
+    }
+
+    /// Sets the names of unit variants which are omitted, e.g. a variant
+    /// meaning "unset".
+    ///
+    /// Variants are matched by name after `#[serde(rename)]`, regardless of
+    /// the enum they belong to. A skipped variant in a sequence drops only
+    /// that element.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// enum Sort {
+    ///     None,
+    ///     Date,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search {
+    ///     q: &'static str,
+    ///     sort: Sort,
+    /// }
+    ///
+    /// let config = Config::new().skip_variants(&["None"]);
+    /// let search = Search { q: "rust", sort: Sort::None };
+    /// assert_eq!(config.to_string(&search).unwrap(), "q=rust");
+    /// let search = Search { q: "rust", sort: Sort::Date };
+    /// assert_eq!(config.to_string(&search).unwrap(), "q=rust&sort=Date");
+    /// ```
+    pub fn skip_variants(mut self, variants: &[&str]) -> Self {
+        self.skip_variants = variants
+            .iter()
+            .map(|&variant| String::from(variant))
+            .collect();
+        self
+    }
+
+    /// Sets parameters which are always appended after the serialized value.
+    ///
+    /// Values are percent-encoded like any other string value.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search {
+    ///     q: &'static str,
+    /// }
+    ///
+    /// let config = Config::new().extra_params(&[("api_version", "2")]);
+    /// assert_eq!(
+    ///     config.to_string(&Search { q: "rust" }).unwrap(),
+    ///     "q=rust&api_version=2"
+    /// );
+    /// ```
+    pub fn extra_params(mut self, params: &[(&str, &str)]) -> Self {
+        self.extra_params = params
+            .iter()
+            .map(|&(key, value)| (String::from(key), String::from(value)))
+            .collect();
+        self
+    }
+
+    /// Omits numeric values equal to zero, as if they were `None`.
+    ///
+    /// This applies only to integer and floating point values (including
+    /// `-0.0`), not to strings like `"0"`.
+    pub fn skip_zero_numbers(mut self, skip: bool) -> Self {
+        self.skip_zero_numbers = skip;
+        self
+    }
+
+    /// Trims leading and trailing ASCII whitespace from each string value
+    /// before encoding.
+    ///
+    /// This only affects string and `char` values, not keys. A value which
+    /// consists of whitespace only is written as empty value.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     name: &'static str,
+    /// }
+    ///
+    /// let config = Config::new().trim_values(true);
+    /// let params = Params { name: "  hello  " };
+    /// assert_eq!(config.to_string(&params).unwrap(), "name=hello");
+    /// ```
+    pub fn trim_values(mut self, trim: bool) -> Self {
+        self.trim_values = trim;
+        self
+    }
+
+    /// Normalizes the line breaks in string values before encoding.
+    ///
+    /// Like [`trim_values`](Config::trim_values), this only affects string
+    /// and `char` values. Trimming is applied first.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, NewlinePolicy};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Comment {
+    ///     text: &'static str,
+    /// }
+    ///
+    /// let comment = Comment { text: "a\r\nb\nc" };
+    /// let config = Config::new().newline_normalization(NewlinePolicy::Lf);
+    /// assert_eq!(config.to_string(&comment).unwrap(), "text=a%0Ab%0Ac");
+    /// ```
+    pub fn newline_normalization(mut self, policy: NewlinePolicy) -> Self {
+        self.newline_normalization = policy;
+        self
+    }
+
+    /// Limits the number of elements of each sequence.
+    ///
+    /// Serialization fails with a custom error (annotated with the path of the
+    /// sequence) when a sequence has more than `max_len` elements. This is a
+    /// safety valve against serializing huge user-supplied collections into a
+    /// URL.
+    pub fn max_seq_len(mut self, max_len: usize) -> Self {
+        self.max_seq_len = Some(max_len);
+        self
+    }
+
+    /// Limits the length of each key in bytes.
+    ///
+    /// The limit applies to the key as written, i.e. including suffixes of
+    /// the [`ArrayFormat`] and after percent-encoding. Serialization fails
+    /// with a custom error (annotated with the path of the value) when a key
+    /// is longer than `max_len`. This guards against pathological keys, e.g.
+    /// of maps built from untrusted input.
+    pub fn max_key_len(mut self, max_len: usize) -> Self {
+        self.max_key_len = Some(max_len);
+        self
+    }
+
+    /// Rejects constructs which cannot be unambiguously parsed back.
+    ///
+    /// In strict mode, serialization fails on:
+    ///
+    /// * an empty sequence under a key, which would otherwise be dropped,
+    /// * a sequence nested in another sequence under a key, which would
+    ///   otherwise be flattened into one list of repeated keys,
+    /// * the same key being emitted by different struct fields or map entries
+    ///   (repeated keys of the elements of one sequence are fine).
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Rejects unit values, i.e. `()` and unit structs, under a key.
+    ///
+    /// Such values emit nothing by default, which may hide a modeling
+    /// mistake. With this option, serialization fails instead, whatever the
+    /// [`UnitFormat`]. The error is an [`Error::Path`] with the path of the
+    /// value, wrapping an [`Error::Unsupported`]. Note that `PhantomData` is a
+    /// unit struct as well.
+    ///
+    /// [`Error::Path`]: ../error/enum.Error.html#variant.Path
+    /// [`Error::Unsupported`]: ../error/enum.Error.html#variant.Unsupported
+    pub fn strict_units(mut self, strict: bool) -> Self {
+        self.strict_units = strict;
+        self
+    }
+
+    /// Sorts the elements of each sequence by their encoded values.
+    ///
+    /// This makes the output deterministic for collections without a stable
+    /// iteration order, like `HashSet`. Elements are compared by their
+    /// percent-encoded output, i.e. lexicographically, so `10` sorts before
+    /// `9`. Sorting applies only to [`ArrayFormat::Repeated`] and
+    /// [`ArrayFormat::Comma`]; the elements are buffered until the end of the
+    /// sequence.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    /// use std::collections::HashSet;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     tag: HashSet<&'static str>,
+    /// }
+    ///
+    /// let params = Params {
+    ///     tag: vec!["b", "c", "a"].into_iter().collect(),
+    /// };
+    /// let config = Config::new().sort_seq_values(true);
+    /// assert_eq!(config.to_string(&params).unwrap(), "tag=a&tag=b&tag=c");
+    /// ```
+    pub fn sort_seq_values(mut self, sort: bool) -> Self {
+        self.sort_seq_values = sort;
+        self
+    }
+
+    /// Drops duplicate elements of sequences joined by
+    /// [`ArrayFormat::Comma`], keeping the first occurrence of each value.
+    ///
+    /// This is for APIs rejecting lists with duplicates. Elements are
+    /// compared by their percent-encoded output.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{ArrayFormat, Config};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     tags: Vec<&'static str>,
+    /// }
+    ///
+    /// let params = Params {
+    ///     tags: vec!["b", "a", "b"],
+    /// };
+    /// let config = Config::new()
+    ///     .array_format(ArrayFormat::Comma)
+    ///     .comma_dedup(true);
+    /// assert_eq!(config.to_string(&params).unwrap(), "tags=b,a");
+    /// ```
+    pub fn comma_dedup(mut self, dedup: bool) -> Self {
+        self.comma_dedup = dedup;
+        self
+    }
+
+    /// Sets whether the commas between the elements of sequences joined by
+    /// [`ArrayFormat::Comma`] are written literally (default) or encoded as
+    /// `%2C`.
+    ///
+    /// The elements are encoded separately in both cases, so a comma within
+    /// an element is always encoded. Note that with encoded separators, the
+    /// elements cannot be told apart from a single value containing commas.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{ArrayFormat, Config};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     scope: Vec<&'static str>,
+    /// }
+    ///
+    /// let params = Params {
+    ///     scope: vec!["openid", "profile"],
+    /// };
+    /// let config = Config::new().array_format(ArrayFormat::Comma);
+    /// assert_eq!(config.to_string(&params).unwrap(), "scope=openid,profile");
+    /// let config = config.comma_literal(false);
+    /// assert_eq!(config.to_string(&params).unwrap(), "scope=openid%2Cprofile");
+    /// ```
+    pub fn comma_literal(mut self, literal: bool) -> Self {
+        self.comma_encoded = !literal;
+        self
+    }
+
+    /// Sets what happens when different fields or map entries emit the same
+    /// key.
+    ///
+    /// Strict mode implies [`KeyCollision::Error`]. Dropped parameters are
+    /// reported by [`Config::to_string_with_report`].
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, KeyCollision};
+    /// use std::collections::BTreeMap;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     id: u32,
+    ///     #[serde(flatten)]
+    ///     extra: BTreeMap<&'static str, u32>,
+    /// }
+    ///
+    /// let mut extra = BTreeMap::new();
+    /// extra.insert("id", 2);
+    /// let params = Params { id: 1, extra };
+    /// let config = Config::new().key_collision(KeyCollision::FirstWins);
+    /// assert_eq!(config.to_string(&params).unwrap(), "id=1");
+    /// let config = Config::new().key_collision(KeyCollision::LastWins);
+    /// assert_eq!(config.to_string(&params).unwrap(), "id=2");
+    /// ```
+    pub fn key_collision(mut self, policy: KeyCollision) -> Self {
+        self.key_collision = policy;
+        self
+    }
+
+    /// Coalesces the values of a key emitted by different struct fields or map
+    /// entries into a single parameter, separated by a literal comma:
+    /// `filter=a,b`.
+    ///
+    /// The values of the elements of a single sequence are only coalesced if
+    /// the key is emitted by another field as well. The coalesced parameter is
+    /// written at the position of the key's first occurrence; for this, all
+    /// parameters are buffered until the end of serialization. Keys are
+    /// grouped after applying the [`key_mapper`](Config::key_mapper). Since
+    /// no key is emitted by different fields then, the [`KeyCollision`]
+    /// policy has no effect. [`Raw`](../helpers/struct.Raw.html) fragments are buffered
+    /// as well, so they keep their position, but are not coalesced.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search {
+    ///     #[serde(rename = "filter")]
+    ///     status: &'static str,
+    ///     page: u32,
+    ///     #[serde(rename = "filter")]
+    ///     owner: &'static str,
+    /// }
+    ///
+    /// let search = Search {
+    ///     status: "open",
+    ///     page: 2,
+    ///     owner: "me",
+    /// };
+    /// let config = Config::new().coalesce_duplicate_keys(true);
+    /// assert_eq!(config.to_string(&search).unwrap(), "filter=open,me&page=2");
+    /// ```
+    pub fn coalesce_duplicate_keys(mut self, coalesce: bool) -> Self {
+        self.coalesce_duplicate_keys = coalesce;
+        self
+    }
+
+    /// Appends a `&` after the last parameter, as some endpoints expect.
+    ///
+    /// Nothing is appended if no parameter was written, so the output stays
+    /// empty.
+    pub fn trailing_separator(mut self, trailing: bool) -> Self {
+        self.trailing_separator = trailing;
+        self
+    }
+
+    /// Sets how a scalar value at the top level is handled.
+    ///
+    /// This helps generic code which may receive either a struct or a scalar.
+    ///
+    /// ```rust
+    /// use serde_url_params::{Config, TopLevelPolicy};
+    ///
+    /// assert!(Config::new().to_string(&42).is_err());
+    /// let config = Config::new().top_level_scalar(TopLevelPolicy::Empty);
+    /// assert_eq!(config.to_string(&42).unwrap(), "");
+    /// ```
+    pub fn top_level_scalar(mut self, policy: TopLevelPolicy) -> Self {
+        self.top_level_scalar = policy;
+        self
+    }
+
+    /// Sets how an output without any parameter is handled.
+    ///
+    /// Extra parameters count as parameters, so they never leave the output
+    /// empty.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, EmptyOutputPolicy};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     q: Option<&'static str>,
+    /// }
+    ///
+    /// let params = Params { q: None };
+    /// assert_eq!(Config::new().to_string(&params).unwrap(), "");
+    /// let config = Config::new().empty_output(EmptyOutputPolicy::Error);
+    /// assert!(config.to_string(&params).is_err());
+    /// ```
+    pub fn empty_output(mut self, policy: EmptyOutputPolicy) -> Self {
+        self.empty_output = policy;
+        self
+    }
+
+    /// Sets the order in which the parameters of struct fields and map
+    /// entries are written.
+    ///
+    /// With [`coalesce_duplicate_keys`](Config::coalesce_duplicate_keys), a
+    /// coalesced parameter moves with the field or entry which emitted its key
+    /// first.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, Order};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     page: u32,
+    ///     filter: &'static str,
+    ///     cursor: u32,
+    /// }
+    ///
+    /// let params = Params {
+    ///     page: 2,
+    ///     filter: "new",
+    ///     cursor: 7,
+    /// };
+    /// let config = Config::new().order(Order::Reverse);
+    /// assert_eq!(config.to_string(&params).unwrap(), "cursor=7&filter=new&page=2");
+    /// let config = Config::new().order(Order::Sorted);
+    /// assert_eq!(config.to_string(&params).unwrap(), "cursor=7&filter=new&page=2");
+    /// ```
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets the case convention of the keys written for struct fields.
+    ///
+    /// The conversion applies to the fields of structs and of struct
+    /// variants, but not to map keys. Note that a struct with a
+    /// `#[serde(flatten)]` field is serialized as a map, so its keys are
+    /// written unchanged.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, KeyCase};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     user_name: &'static str,
+    ///     page_size: u32,
+    /// }
+    ///
+    /// let params = Params {
+    ///     user_name: "boxdot",
+    ///     page_size: 20,
+    /// };
+    /// let config = Config::new().key_case(KeyCase::Camel);
+    /// assert_eq!(config.to_string(&params).unwrap(), "userName=boxdot&pageSize=20");
+    /// ```
+    pub fn key_case(mut self, case: KeyCase) -> Self {
+        self.key_case = case;
+        self
+    }
+
+    /// Writes the entries of each map sorted by key.
+    ///
+    /// This is [`Order::Sorted`] for maps only; struct fields keep their
+    /// order.
+    ///
+    /// This makes the output of maps without a stable iteration order, like
+    /// `HashMap`, deterministic. Keys are compared as unencoded strings; the
+    /// values of the same key keep their order. Note that a struct with a
+    /// `#[serde(flatten)]` field is serialized as a map, so all of its fields
+    /// are sorted. The entries are buffered until the end of the map.
+    ///
+    /// ```rust
+    /// use serde_url_params::Config;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut params = HashMap::new();
+    /// params.insert("b", 2);
+    /// params.insert("c", 3);
+    /// params.insert("a", 1);
+    /// let config = Config::new().sort_map_keys(true);
+    /// assert_eq!(config.to_string(&params).unwrap(), "a=1&b=2&c=3");
+    /// ```
+    pub fn sort_map_keys(mut self, sort: bool) -> Self {
+        self.sort_map_keys = sort;
+        self
+    }
+
+    /// Sets a callback which is invoked with the key and value of each
+    /// emitted parameter, e.g. for logging or metrics.
+    ///
+    /// The value is passed before percent-encoding, and without the prefix of
+    /// [`NewtypeVariantFormat::TaggedColon`]; a parameter without value is
+    /// passed with an empty value. Elements of [`ArrayFormat::Comma`] are
+    /// passed once joined. The output is not affected. The callback is shared
+    /// by clones of this configuration.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search {
+    ///     q: &'static str,
+    ///     page: u32,
+    /// }
+    ///
+    /// let pairs = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = Arc::clone(&pairs);
+    /// let config = Config::new().on_pair(move |key, value| {
+    ///     sink.lock().unwrap().push(format!("{}: {}", key, value));
+    /// });
+    /// let search = Search { q: "a&b", page: 2 };
+    /// assert_eq!(config.to_string(&search).unwrap(), "q=a%26b&page=2");
+    /// assert_eq!(*pairs.lock().unwrap(), ["q: a&b", "page: 2"]);
+    /// ```
+    pub fn on_pair<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&str, &str) + Send + 'static,
+    {
+        self.on_pair = Some(PairCallback(Arc::new(Mutex::new(callback))));
+        self
+    }
+
+    /// Sets a callback which renames each emitted key.
+    ///
+    /// The callback receives the final key before percent-encoding, i.e.
+    /// after [`key_case`](Config::key_case) and including the suffixes of the
+    /// [`ArrayFormat`], e.g. `ids[0]`. Key collisions and the length limit of
+    /// [`max_key_len`](Config::max_key_len) are checked on the renamed keys,
+    /// while the grouping of
+    /// [`coalesce_duplicate_keys`](Config::coalesce_duplicate_keys) is
+    /// determined by the keys before renaming. The callback is shared by
+    /// clones of this configuration.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::Config;
+    /// use std::borrow::Cow;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search {
+    ///     q: &'static str,
+    ///     page: u32,
+    /// }
+    ///
+    /// let config = Config::new().key_mapper(|key| match key {
+    ///     "q" => Cow::Borrowed("query"),
+    ///     key => Cow::Owned(format!("search.{}", key)),
+    /// });
+    /// let search = Search { q: "rust", page: 2 };
+    /// assert_eq!(config.to_string(&search).unwrap(), "query=rust&search.page=2");
+    /// ```
+    pub fn key_mapper<F>(mut self, mapper: F) -> Self
+    where
+        F: Fn(&str) -> Cow<'_, str> + Send + Sync + 'static,
+    {
+        self.key_mapper = Some(KeyMapper(Arc::new(mapper)));
+        self
+    }
+
+    /// Renames the key by the key mapper, if any.
+    pub(crate) fn map_key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        match self.key_mapper {
+            Some(KeyMapper(ref mapper)) => mapper(key),
+            None => Cow::Borrowed(key),
+        }
+    }
+
+    /// Serialize the given data structure as URL parameters into the IO
+    /// stream using this configuration.
+    ///
+    /// The writer is flushed before returning. See
+    /// [`to_writer`](../ser/fn.to_writer.html) for possible errors.
+    pub fn to_writer<W, T>(&self, writer: W, value: &T) -> Result<()>
+    where
+        W: io::Write,
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        let mut ser = Serializer::new_with_config(writer, self.clone());
+        ser.prepare(value)?;
+        value.serialize(&mut ser)?;
+        ser.finish()
+    }
+
+    /// Serialize the given data structure as a byte vector containing URL
+    /// parameters using this configuration.
+    ///
+    /// See [`to_vec`](../ser/fn.to_vec.html) for possible errors.
+    pub fn to_vec<T>(&self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        let mut writer = Vec::with_capacity(128);
+        self.to_writer(&mut writer, value)?;
+        Ok(writer)
+    }
+
+    /// Serialize the given data structure as a String of URL parameters using
+    /// this configuration.
+    ///
+    /// See [`to_string`](../ser/fn.to_string.html) for possible errors.
+    pub fn to_string<T>(&self, value: &T) -> Result<String>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        let vec = self.to_vec(value)?;
+        let string = String::from_utf8(vec)?;
+        Ok(string)
+    }
+
+    /// Returns the length in bytes of the URL parameters the given data
+    /// structure serializes to using this configuration, without building
+    /// the output.
+    ///
+    /// See [`serialized_len`](../ser/fn.serialized_len.html).
+    pub fn serialized_len<T>(&self, value: &T) -> Result<usize>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        crate::ser::serialized_len_config(value, self.clone())
+    }
+
+    /// Serialize the given data structure as a String of URL parameters using
+    /// this configuration, if the predicate holds for it.
+    ///
+    /// See [`to_string_if`](../ser/fn.to_string_if.html).
+    pub fn to_string_if<T, F>(&self, value: &T, predicate: F) -> Result<Option<String>>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+        F: FnOnce(&T) -> bool,
+    {
+        if !predicate(value) {
+            return Ok(None);
+        }
+        self.to_string(value).map(Some)
+    }
+
+    /// Serialize the given data structure into decoded key value pairs using
+    /// this configuration.
+    ///
+    /// See [`to_pairs`](../ser/fn.to_pairs.html).
+    pub fn to_pairs<T>(&self, value: &T) -> Result<Vec<(String, String)>>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        crate::ser::to_pairs_config(value, self.clone(), false)
+    }
+
+    /// Serialize the given data structure into key value pairs using this
+    /// configuration, percent-encoded exactly as in the output.
+    ///
+    /// See [`to_encoded_pairs`](../ser/fn.to_encoded_pairs.html).
+    pub fn to_encoded_pairs<T>(&self, value: &T) -> Result<Vec<(String, String)>>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        crate::ser::to_pairs_config(value, self.clone(), true)
+    }
+
+    /// Serialize the given data structure as a String of URL parameters
+    /// using this configuration, encoded by
+    /// `url::form_urlencoded::Serializer`.
+    ///
+    /// The options of this configuration which change the percent-encoding,
+    /// e.g. the [`EncodeSet`], do not apply. See
+    /// [`to_form_urlencoded`](../ser/fn.to_form_urlencoded.html).
+    pub fn to_form_urlencoded<T>(&self, value: &T) -> Result<String>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        let pairs = self.to_pairs(value)?;
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        serializer.extend_pairs(pairs);
+        Ok(serializer.finish())
+    }
+
+    /// Serialize the given data structure into a map from each key to its
+    /// decoded values using this configuration.
+    ///
+    /// See [`to_map`](../ser/fn.to_map.html).
+    pub fn to_map<T>(&self, value: &T) -> Result<BTreeMap<String, Vec<String>>>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        let mut map = BTreeMap::<_, Vec<_>>::new();
+        for (key, value) in self.to_pairs(value)? {
+            map.entry(key).or_default().push(value);
+        }
+        Ok(map)
+    }
+
+    /// Serialize the given data structure as a String of URL parameters using
+    /// this configuration, and report all values which were dropped.
+    ///
+    /// See [`to_string_with_report`](../ser/fn.to_string_with_report.html).
+    pub fn to_string_with_report<T>(&self, value: &T) -> Result<(String, Vec<Warning>)>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        crate::ser::to_string_with_report_config(value, self.clone())
+    }
+
+    /// Serialize the given data structure as a String of URL parameters using
+    /// this configuration, and explain the outcome of each top level field.
+    ///
+    /// See [`to_string_explained`](../ser/fn.to_string_explained.html).
+    pub fn to_string_explained<T>(&self, value: &T) -> Result<(String, Vec<Explanation>)>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        crate::ser::to_string_explained_config(value, self.clone())
+    }
+}
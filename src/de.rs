@@ -0,0 +1,495 @@
+//! Deserialize URL parameters string into a Rust data structure.
+
+use crate::error::{Error, Result};
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, IntoDeserializer, Visitor};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A structure for deserializing URL parameters string into Rust values.
+///
+/// Both keys and values are percent-decoded, so struct fields and map keys are
+/// matched against the decoded keys. The parameters are grouped by key,
+/// preserving the order in which the keys first occur. Repeated keys are
+/// collected into a sequence, so they can be deserialized e.g. into a `Vec`.
+pub struct Deserializer<'de> {
+    params: Vec<(Cow<'de, str>, Vec<Cow<'de, str>>)>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer from URL parameters string.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Self {
+        Self::from_bytes(input.as_bytes())
+    }
+
+    /// Creates a deserializer from URL parameters bytes.
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        let mut params: Vec<(Cow<'de, str>, Vec<Cow<'de, str>>)> = Vec::new();
+        let mut positions: HashMap<Cow<'de, str>, usize> = HashMap::new();
+        for (key, value) in url::form_urlencoded::parse(input) {
+            match positions.get(&key) {
+                Some(&pos) => params[pos].1.push(value),
+                None => {
+                    positions.insert(key.clone(), params.len());
+                    params.push((key, vec![value]));
+                }
+            }
+        }
+        Deserializer { params }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let params = self
+            .params
+            .into_iter()
+            .map(|(key, values)| (Part(key), Values(values)));
+        let mut map = MapDeserializer::new(params);
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct seq tuple tuple_struct enum
+        identifier ignored_any
+    }
+}
+
+/// All values of a single key.
+struct Values<'de>(Vec<Cow<'de, str>>);
+
+impl<'de> Values<'de> {
+    /// The value of a key which is expected to occur only once. If it occurs
+    /// several times, the last value wins.
+    fn single(mut self) -> Part<'de> {
+        Part(self.0.pop().unwrap_or_default())
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Values<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                self.single().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Values<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.0.len() > 1 {
+            self.deserialize_seq(visitor)
+        } else {
+            self.single().deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut seq = SeqDeserializer::new(self.0.into_iter().map(Part));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.single().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.single().deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.single().deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16
+        deserialize_u32 deserialize_u64 deserialize_u128 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_unit deserialize_map
+        deserialize_identifier deserialize_ignored_any
+    }
+}
+
+/// A single percent-decoded key or value.
+struct Part<'de>(Cow<'de, str>);
+
+impl<'de> IntoDeserializer<'de, Error> for Part<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! forward_parsed_value {
+    ($($ty:ident => $method:ident,)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                match self.0.parse::<$ty>() {
+                    Ok(value) => {
+                        de::Deserializer::$method(value.into_deserializer(), visitor)
+                    }
+                    Err(err) => Err(de::Error::custom(format_args!(
+                        "invalid value `{}`: {}",
+                        self.0, err
+                    ))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Part<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // Only unit variants can be represented by a single value.
+        visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(self.0))
+    }
+
+    forward_parsed_value! {
+        bool => deserialize_bool,
+        i8 => deserialize_i8,
+        i16 => deserialize_i16,
+        i32 => deserialize_i32,
+        i64 => deserialize_i64,
+        i128 => deserialize_i128,
+        u8 => deserialize_u8,
+        u16 => deserialize_u16,
+        u32 => deserialize_u32,
+        u64 => deserialize_u64,
+        u128 => deserialize_u128,
+        f32 => deserialize_f32,
+        f64 => deserialize_f64,
+        char => deserialize_char,
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+/// Deserialize an instance of type `T` from a string of URL parameters.
+///
+/// ```rust
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, PartialEq, Deserialize)]
+/// struct Params {
+///     id: u32,
+///     filter: Vec<String>,
+/// }
+///
+/// let params: Params = serde_url_params::from_str("id=7&filter=a+b&filter=c").unwrap();
+/// assert_eq!(
+///     params,
+///     Params {
+///         id: 7,
+///         filter: vec!["a b".into(), "c".into()],
+///     }
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Deserialization fails if:
+///
+/// * `T`'s implementation of `Deserialize` decides to fail,
+/// * a value cannot be parsed into the type of the corresponding field,
+/// * a required key is missing.
+pub fn from_str<'de, T>(input: &'de str) -> Result<T>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(Deserializer::from_str(input))
+}
+
+/// Deserialize an instance of type `T` from bytes of URL parameters.
+///
+/// # Errors
+///
+/// Same as [`from_str`](fn.from_str.html).
+pub fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(Deserializer::from_bytes(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::from_str;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_struct() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Params {
+            id: String,
+            filter: Vec<String>,
+            option: Option<String>,
+            num: Option<usize>,
+            flag: bool,
+            pair: (u32, f64),
+        }
+        let params: Params =
+            from_str("id=some%20id&filter=a&num=42&filter=b&flag=true&pair=1&pair=2.5").unwrap();
+        assert_eq!(
+            params,
+            Params {
+                id: String::from("some id"),
+                filter: vec![String::from("a"), String::from("b")],
+                option: None,
+                num: Some(42),
+                flag: true,
+                pair: (1, 2.5),
+            }
+        );
+        assert!(from_str::<Params>("id=x&flag=maybe&pair=1&pair=2").is_err());
+    }
+
+    #[test]
+    fn test_map_of_vecs() {
+        let params: HashMap<String, Vec<String>> = from_str("a=1&a=2&b=3").unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params["a"], ["1", "2"]);
+        assert_eq!(params["b"], ["3"]);
+    }
+
+    #[test]
+    fn test_unit_variant_round_trip() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Selection {
+            A,
+            #[serde(rename = "b b")]
+            B,
+        }
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Params {
+            select: Selection,
+            select2: Vec<Selection>,
+        }
+        let params = Params {
+            select: Selection::A,
+            select2: vec![Selection::B, Selection::A],
+        };
+        let query = crate::to_string(&params).unwrap();
+        assert_eq!(query, "select=A&select2=b+b&select2=A");
+        assert_eq!(from_str::<Params>(&query).unwrap(), params);
+        assert!(from_str::<Params>("select=C&select2=A").is_err());
+    }
+
+    #[test]
+    fn test_encoded_keys() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Params {
+            #[serde(rename = "weird&key")]
+            weird: String,
+            #[serde(rename = "a b")]
+            ab: Vec<u32>,
+        }
+        let params: Params = from_str("weird%26key=v%3D&a+b=1&a%20b=2").unwrap();
+        assert_eq!(
+            params,
+            Params {
+                weird: String::from("v="),
+                ab: vec![1, 2],
+            }
+        );
+
+        let mut query = crate::QueryParams::new();
+        query.append("weird&key", "x");
+        query.append("a b", "3");
+        let query = query.to_string();
+        assert_eq!(query, "weird%26key=x&a+b=3");
+        let params: HashMap<String, String> = from_str(&query).unwrap();
+        assert_eq!(params["weird&key"], "x");
+        assert_eq!(params["a b"], "3");
+    }
+
+    #[test]
+    fn test_default_fields() {
+        fn default_page() -> u32 {
+            1
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Params {
+            q: String,
+            #[serde(default)]
+            tags: Vec<String>,
+            #[serde(default = "default_page")]
+            page: u32,
+        }
+        let params: Params = from_str("q=rust").unwrap();
+        assert_eq!(
+            params,
+            Params {
+                q: String::from("rust"),
+                tags: vec![],
+                page: 1,
+            }
+        );
+        let params: Params = from_str("page=3&q=rust&tags=a").unwrap();
+        assert_eq!(params.tags, ["a"]);
+        assert_eq!(params.page, 3);
+        let err = from_str::<Params>("page=3").unwrap_err();
+        assert_eq!(err.to_string(), "missing field `q`");
+
+        #[derive(Debug, Default, PartialEq, Deserialize)]
+        #[serde(default)]
+        struct Options {
+            verbose: bool,
+            limit: Option<u32>,
+        }
+        assert_eq!(from_str::<Options>("").unwrap(), Options::default());
+        assert!(from_str::<Options>("verbose=true").unwrap().verbose);
+    }
+}
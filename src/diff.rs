@@ -0,0 +1,295 @@
+//! Serialize only the fields of a struct which differ from their defaults.
+
+use serde::ser::{self, Serialize, SerializeStruct, Serializer};
+use std::collections::HashMap;
+
+/// Wrapper serializing only the fields of a struct whose values differ from
+/// the corresponding fields of a default value.
+///
+/// This is useful e.g. for PATCH-like requests. Serde does not expose field
+/// defaults at serialization time, so the default value is serialized as well
+/// and the fields are compared by their serialized form.
+///
+/// `T` must be a struct (without flattened fields) implementing
+/// `PartialEq + Default`. If the whole value equals its default, nothing is
+/// emitted at all.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::DiffSerialize;
+///
+/// #[derive(Default, PartialEq, Serialize)]
+/// struct Settings {
+///     theme: String,
+///     page_size: u32,
+///     notify: bool,
+/// }
+///
+/// let settings = Settings {
+///     page_size: 50,
+///     ..Default::default()
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&DiffSerialize::new(&settings)).unwrap(),
+///     "page_size=50"
+/// );
+/// ```
+pub struct DiffSerialize<'a, T> {
+    value: &'a T,
+    default: T,
+}
+
+impl<'a, T> DiffSerialize<'a, T>
+where
+    T: PartialEq + Default,
+{
+    /// Creates a wrapper comparing `value` with `T::default()`.
+    pub fn new(value: &'a T) -> Self {
+        Self::with_default(value, T::default())
+    }
+
+    /// Creates a wrapper comparing `value` with the given `default`.
+    pub fn with_default(value: &'a T, default: T) -> Self {
+        DiffSerialize { value, default }
+    }
+}
+
+impl<'a, T> Serialize for DiffSerialize<'a, T>
+where
+    T: Serialize + PartialEq,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if *self.value == self.default {
+            return serializer.serialize_unit();
+        }
+        let mut defaults = HashMap::new();
+        {
+            let mut sink = crate::ser::Serializer::new(std::io::sink());
+            let recorder = FieldFilter {
+                inner: &mut sink,
+                mode: Mode::Record(&mut defaults),
+            };
+            self.default
+                .serialize(recorder)
+                .map_err(ser::Error::custom)?;
+        }
+        self.value.serialize(FieldFilter {
+            inner: serializer,
+            mode: Mode::Compare(&defaults),
+        })
+    }
+}
+
+/// Serialized form of each field of the default value.
+type Fields = HashMap<&'static str, Vec<u8>>;
+
+enum Mode<'a> {
+    /// Record the serialized form of each field.
+    Record(&'a mut Fields),
+    /// Skip each field whose serialized form matches the recorded one.
+    Compare(&'a Fields),
+}
+
+/// Serializer forwarding everything to `inner`, but filtering struct fields.
+struct FieldFilter<'a, S> {
+    inner: S,
+    mode: Mode<'a>,
+}
+
+struct FilteredStruct<'a, S> {
+    inner: S,
+    mode: Mode<'a>,
+}
+
+impl<'a, S> SerializeStruct for FilteredStruct<'a, S>
+where
+    S: SerializeStruct,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let serialized = crate::ser::to_vec_keyed(key, value).ok();
+        match (&mut self.mode, serialized) {
+            (Mode::Record(fields), Some(serialized)) => {
+                fields.insert(key, serialized);
+            }
+            (Mode::Compare(fields), Some(serialized)) if fields.get(key) == Some(&serialized) => {
+                return self.inner.skip_field(key);
+            }
+            _ => (),
+        }
+        self.inner.serialize_field(key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+macro_rules! forward {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<$ret, S::Error> {
+                self.inner.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<'a, S> Serializer for FieldFilter<'a, S>
+where
+    S: Serializer,
+{
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    type SerializeSeq = S::SerializeSeq;
+    type SerializeTuple = S::SerializeTuple;
+    type SerializeTupleStruct = S::SerializeTupleStruct;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = S::SerializeMap;
+    type SerializeStruct = FilteredStruct<'a, S::SerializeStruct>;
+    type SerializeStructVariant = S::SerializeStructVariant;
+
+    forward! {
+        serialize_bool(v: bool) -> S::Ok;
+        serialize_i8(v: i8) -> S::Ok;
+        serialize_i16(v: i16) -> S::Ok;
+        serialize_i32(v: i32) -> S::Ok;
+        serialize_i64(v: i64) -> S::Ok;
+        serialize_i128(v: i128) -> S::Ok;
+        serialize_u8(v: u8) -> S::Ok;
+        serialize_u16(v: u16) -> S::Ok;
+        serialize_u32(v: u32) -> S::Ok;
+        serialize_u64(v: u64) -> S::Ok;
+        serialize_u128(v: u128) -> S::Ok;
+        serialize_f32(v: f32) -> S::Ok;
+        serialize_f64(v: f64) -> S::Ok;
+        serialize_char(v: char) -> S::Ok;
+        serialize_str(v: &str) -> S::Ok;
+        serialize_bytes(v: &[u8]) -> S::Ok;
+        serialize_none() -> S::Ok;
+        serialize_unit() -> S::Ok;
+        serialize_unit_struct(name: &'static str) -> S::Ok;
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str) -> S::Ok;
+        serialize_seq(len: Option<usize>) -> S::SerializeSeq;
+        serialize_tuple(len: usize) -> S::SerializeTuple;
+        serialize_tuple_struct(name: &'static str, len: usize) -> S::SerializeTupleStruct;
+        serialize_tuple_variant(
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize
+        ) -> S::SerializeTupleVariant;
+        serialize_map(len: Option<usize>) -> S::SerializeMap;
+        serialize_struct_variant(
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize
+        ) -> S::SerializeStructVariant;
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner.serialize_some(value)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.inner
+            .serialize_newtype_variant(name, index, variant, value)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(FilteredStruct {
+            inner: self.inner.serialize_struct(name, len)?,
+            mode: self.mode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiffSerialize;
+    use crate::{to_string, BoolFormat, Config};
+    use serde::Serialize;
+
+    #[derive(Debug, Default, PartialEq, Serialize)]
+    struct Patch {
+        name: Option<String>,
+        tags: Vec<String>,
+        limit: u32,
+        active: bool,
+    }
+
+    #[test]
+    fn test_diff_serialize() {
+        let patch = Patch::default();
+        assert_eq!(to_string(&DiffSerialize::new(&patch)).unwrap(), "");
+
+        let patch = Patch {
+            tags: vec![String::from("a"), String::from("b")],
+            active: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_string(&DiffSerialize::new(&patch)).unwrap(),
+            "tags=a&tags=b&active=true"
+        );
+        let config = Config::new().bool_format(BoolFormat::FlagIfTrue);
+        assert_eq!(
+            config.to_string(&DiffSerialize::new(&patch)).unwrap(),
+            "tags=a&tags=b&active"
+        );
+    }
+
+    #[test]
+    fn test_diff_serialize_with_default() {
+        let default = Patch {
+            limit: 10,
+            ..Default::default()
+        };
+        let patch = Patch {
+            name: Some(String::from("x")),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            to_string(&DiffSerialize::with_default(&patch, default)).unwrap(),
+            "name=x"
+        );
+    }
+}
@@ -1,11 +1,12 @@
-//! When serializing to URL parameters fails.
+//! When serializing to or deserializing from URL parameters fails.
 
-use serde::ser;
+use serde::{de, ser};
 use std::fmt;
 
 #[derive(Debug)]
-/// Represents all possible errors that can occur when serializing into URL
-/// parameters.
+/// Represents all possible errors that can occur when serializing into or
+/// deserializing from URL parameters.
+#[non_exhaustive]
 pub enum Error {
     /// External error caused by e.g. utf8 string conversion or io.
     Extern(Box<dyn std::error::Error + Send + Sync>),
@@ -13,6 +14,16 @@ pub enum Error {
     Unsupported(String),
     /// Custom error caused by any error while serializing a type.
     Custom(String),
+    /// Error annotated with the path of the value at which it occurred.
+    ///
+    /// Its message includes the message of the underlying error, so its
+    /// source is the source of the underlying error, e.g. an io error.
+    Path {
+        /// Path of the value, e.g. `options.inner` or `filter[1]`.
+        path: String,
+        /// The underlying error.
+        error: Box<Error>,
+    },
 }
 
 /// Alias for `Result` with error type `serde_url_params::Error`.
@@ -30,12 +41,25 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Extern(ref err) => fmt::Display::fmt(err, f),
-            Error::Unsupported(ref msg) | Error::Custom(ref msg) => fmt::Display::fmt(msg, f),
+            Error::Unsupported(ref msg) => write!(f, "unsupported {}", msg),
+            Error::Custom(ref msg) => fmt::Display::fmt(msg, f),
+            Error::Path {
+                ref path,
+                ref error,
+            } => write!(f, "{} (at {})", error, path),
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Extern(ref err) => Some(&**err),
+            Error::Path { ref error, .. } => error.source(),
+            _ => None,
+        }
+    }
+}
 
 impl ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Error {
@@ -43,6 +67,12 @@ impl ser::Error for Error {
     }
 }
 
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Custom(msg.to_string())
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Error::Extern(Box::new(err))
@@ -0,0 +1,2229 @@
+//! Helpers for customizing the serialization of single fields.
+//!
+//! The helpers are meant to be used with Serde's field attributes
+//! `#[serde(with = "...")]` resp. `#[serde(serialize_with = "...")]`, or are
+//! wrapper types implementing `Serialize`.
+
+use crate::config::Config;
+use serde::ser::{Error as _, Impossible, Serialize, SerializeMap, SerializeStruct, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use std::time::Duration;
+
+/// Serialize `None` as the literal string `null` instead of omitting it.
+///
+/// `Some(v)` is serialized as `v` would be without the helper.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Update {
+///     #[serde(with = "serde_url_params::helpers::option_as_null")]
+///     email: Option<String>,
+/// }
+///
+/// let update = Update { email: None };
+/// assert_eq!(serde_url_params::to_string(&update).unwrap(), "email=null");
+/// ```
+pub mod option_as_null {
+    use serde::{Serialize, Serializer};
+
+    /// Serializes `None` as `null` and `Some(v)` as `v`.
+    pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        match value {
+            Some(value) => serializer.serialize_some(value),
+            None => serializer.serialize_str("null"),
+        }
+    }
+}
+
+/// Optional value which is omitted when `None`.
+///
+/// This is the default behavior for `Option`, but spelled out explicitly, so
+/// that fields with different `None` handling can be mixed in one struct, see
+/// [`NullIfNone`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OmitIfNone<T>(pub Option<T>);
+
+impl<T> From<Option<T>> for OmitIfNone<T> {
+    fn from(value: Option<T>) -> Self {
+        OmitIfNone(value)
+    }
+}
+
+impl<T: Serialize> Serialize for OmitIfNone<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Some(ref value) => serializer.serialize_some(value),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Optional value which is serialized as `null` when `None`.
+///
+/// This is the wrapper type equivalent of [`option_as_null`].
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::helpers::{NullIfNone, OmitIfNone};
+///
+/// #[derive(Serialize)]
+/// struct Update {
+///     email: NullIfNone<String>,
+///     name: OmitIfNone<String>,
+/// }
+///
+/// let update = Update {
+///     email: None.into(),
+///     name: None.into(),
+/// };
+/// assert_eq!(serde_url_params::to_string(&update).unwrap(), "email=null");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NullIfNone<T>(pub Option<T>);
+
+impl<T> From<Option<T>> for NullIfNone<T> {
+    fn from(value: Option<T>) -> Self {
+        NullIfNone(value)
+    }
+}
+
+impl<T: Serialize> Serialize for NullIfNone<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        option_as_null::serialize(&self.0, serializer)
+    }
+}
+
+/// Serialize a flattened `Option` whose value is `None` as the parameter
+/// `marker`, e.g. `options=`, instead of omitting it.
+///
+/// Serde drops a `None` within `#[serde(flatten)]` before it reaches the
+/// serializer, so the absence of the whole group cannot be detected by
+/// configuration. The helper is therefore applied to the flattened field
+/// itself, via a small wrapper function passing the marker as a `(key,
+/// value)` pair. `Some(v)` is flattened as `v` would be without the helper.
+///
+/// ```rust
+/// use serde::{Serialize, Serializer};
+/// use serde_url_params::helpers::flatten_or_marker;
+///
+/// fn options_marker<S: Serializer>(
+///     value: &Option<Options>,
+///     serializer: S,
+/// ) -> Result<S::Ok, S::Error> {
+///     flatten_or_marker(value, serializer, ("options", ""))
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Options {
+///     verbose: bool,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     id: u32,
+///     #[serde(flatten, serialize_with = "options_marker")]
+///     options: Option<Options>,
+/// }
+///
+/// let params = Params { id: 1, options: None };
+/// assert_eq!(serde_url_params::to_string(&params).unwrap(), "id=1&options=");
+///
+/// let params = Params {
+///     id: 1,
+///     options: Some(Options { verbose: true }),
+/// };
+/// assert_eq!(serde_url_params::to_string(&params).unwrap(), "id=1&verbose=true");
+/// ```
+///
+/// Only flattened fields hold a group of parameters without a key of their
+/// own; for other fields use [`option_as_null`] or
+/// [`Config::none_sentinel`](../config/struct.Config.html#method.none_sentinel).
+pub fn flatten_or_marker<T, S>(
+    value: &Option<T>,
+    serializer: S,
+    marker: (&str, &str),
+) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    match value {
+        Some(value) => value.serialize(serializer),
+        None => {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry(marker.0, marker.1)?;
+            map.end()
+        }
+    }
+}
+
+/// Serialize a value as a nested query string under a single key.
+///
+/// The value is serialized to URL parameters first, and the resulting string
+/// is then percent-encoded as a whole, such that it can be embedded as the
+/// value of one parameter.
+///
+/// The nested value is serialized with the default [`Config`], since a
+/// serialize function has no access to the configuration of the outer
+/// serializer. Options like the encode set or the array format thus only
+/// apply to the outer query; use [`nested_query_with`] to pass a
+/// configuration for the nested one.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Callback {
+///     a: u32,
+///     b: u32,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     #[serde(serialize_with = "serde_url_params::helpers::nested_query")]
+///     callback: Callback,
+/// }
+///
+/// let params = Params {
+///     callback: Callback { a: 1, b: 2 },
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "callback=a%3D1%26b%3D2"
+/// );
+/// ```
+pub fn nested_query<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    use serde::ser::Error;
+    let query = crate::to_string(value).map_err(S::Error::custom)?;
+    serializer.serialize_str(&query)
+}
+
+/// Serialize a value as a nested query string under a single key, with the
+/// given configuration.
+///
+/// Same as [`nested_query`], but the nested value is serialized with
+/// `config`. The helper is applied via a small wrapper function passing the
+/// configuration.
+///
+/// ```rust
+/// use serde::{Serialize, Serializer};
+/// use serde_url_params::helpers::nested_query_with;
+/// use serde_url_params::{ArrayFormat, Config};
+///
+/// fn indexed_query<S: Serializer>(value: &Callback, serializer: S) -> Result<S::Ok, S::Error> {
+///     let config = Config::new().array_format(ArrayFormat::Indexed);
+///     nested_query_with(value, serializer, &config)
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Callback {
+///     ids: Vec<u32>,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     #[serde(serialize_with = "indexed_query")]
+///     callback: Callback,
+/// }
+///
+/// let params = Params {
+///     callback: Callback { ids: vec![1, 2] },
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "callback=ids%5B0%5D%3D1%26ids%5B1%5D%3D2"
+/// );
+/// ```
+pub fn nested_query_with<T, S>(value: &T, serializer: S, config: &Config) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let query = config.to_string(value).map_err(S::Error::custom)?;
+    serializer.serialize_str(&query)
+}
+
+/// Serialize a value as string via its `Display` implementation.
+///
+/// This is meant for arbitrary precision numbers like decimals or big
+/// integers, whose `Serialize` implementation may go through a float, or may
+/// not be available at all. The `Display` output is written exactly, i.e.
+/// without rounding or scientific notation, and percent-encoded like any
+/// other string.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Payment {
+///     #[serde(serialize_with = "serde_url_params::helpers::display_str")]
+///     amount: u128,
+/// }
+///
+/// let payment = Payment {
+///     amount: u128::MAX,
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&payment).unwrap(),
+///     "amount=340282366920938463463374607431768211455"
+/// );
+/// ```
+pub fn display_str<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + fmt::Display,
+    S: Serializer,
+{
+    serializer.collect_str(value)
+}
+
+/// Serialize a byte slice, e.g. `Vec<u8>`, `&[u8]` or `Cow<[u8]>`, as byte
+/// string instead of as sequence of integers.
+///
+/// The bytes are written according to the configured
+/// [`BytesFormat`](../config/enum.BytesFormat.html), e.g. as a single Base64
+/// encoded parameter.
+pub fn bytes<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + AsRef<[u8]>,
+    S: Serializer,
+{
+    serializer.serialize_bytes(value.as_ref())
+}
+
+/// Serialize a `Duration` as ISO 8601 duration, e.g. `PT1H2M3.5S`.
+///
+/// The duration is split into hours, minutes and seconds; zero components are
+/// omitted, except for a zero duration, which is written as `PT0S`. Fractions
+/// of a second are written with up to nine digits, without trailing zeros.
+/// Days are not used, since their length is ambiguous.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use std::time::Duration;
+///
+/// #[derive(Serialize)]
+/// struct Event {
+///     #[serde(serialize_with = "serde_url_params::helpers::iso8601_duration")]
+///     length: Duration,
+/// }
+///
+/// let event = Event {
+///     length: Duration::from_secs(3723),
+/// };
+/// assert_eq!(serde_url_params::to_string(&event).unwrap(), "length=PT1H2M3S");
+/// ```
+pub fn iso8601_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    use fmt::Write;
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let mut output = String::from("PT");
+    if hours > 0 {
+        write!(output, "{}H", hours).map_err(serde::ser::Error::custom)?;
+    }
+    if minutes > 0 {
+        write!(output, "{}M", minutes).map_err(serde::ser::Error::custom)?;
+    }
+    if seconds > 0 || nanos > 0 || output.len() == 2 {
+        write!(output, "{}", seconds).map_err(serde::ser::Error::custom)?;
+        if nanos > 0 {
+            let fraction = format!("{:09}", nanos);
+            output.push('.');
+            output.push_str(fraction.trim_end_matches('0'));
+        }
+        output.push('S');
+    }
+    serializer.serialize_str(&output)
+}
+
+/// Unit in which [`duration`] writes a `Duration`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DurationUnit {
+    /// Whole seconds.
+    Secs,
+    /// Whole milliseconds.
+    Millis,
+    /// Whole microseconds.
+    Micros,
+    /// Whole nanoseconds.
+    Nanos,
+}
+
+/// Serialize a `Duration` as an integer number of `unit`s, e.g. `1500` for
+/// one and a half seconds in milliseconds.
+///
+/// Precision finer than the unit is truncated, so the same duration is
+/// written as `1` in seconds. For `serialize_with`, use one of
+/// [`duration_secs`], [`duration_millis`], [`duration_micros`] and
+/// [`duration_nanos`].
+pub fn duration<S>(
+    duration: &Duration,
+    serializer: S,
+    unit: DurationUnit,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let value = match unit {
+        DurationUnit::Secs => u128::from(duration.as_secs()),
+        DurationUnit::Millis => duration.as_millis(),
+        DurationUnit::Micros => duration.as_micros(),
+        DurationUnit::Nanos => duration.as_nanos(),
+    };
+    match u64::try_from(value) {
+        Ok(value) => serializer.serialize_u64(value),
+        Err(_) => serializer.serialize_u128(value),
+    }
+}
+
+/// Serialize a `Duration` as whole seconds.
+///
+/// See [`duration`].
+pub fn duration_secs<S>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    duration(value, serializer, DurationUnit::Secs)
+}
+
+/// Serialize a `Duration` as whole milliseconds, as many APIs expect for
+/// timeouts.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use std::time::Duration;
+///
+/// #[derive(Serialize)]
+/// struct Request {
+///     #[serde(serialize_with = "serde_url_params::helpers::duration_millis")]
+///     timeout: Duration,
+/// }
+///
+/// let request = Request {
+///     timeout: Duration::from_millis(1500),
+/// };
+/// assert_eq!(serde_url_params::to_string(&request).unwrap(), "timeout=1500");
+/// ```
+pub fn duration_millis<S>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    duration(value, serializer, DurationUnit::Millis)
+}
+
+/// Serialize a `Duration` as whole microseconds.
+///
+/// See [`duration`].
+pub fn duration_micros<S>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    duration(value, serializer, DurationUnit::Micros)
+}
+
+/// Serialize a `Duration` as whole nanoseconds.
+///
+/// See [`duration`].
+pub fn duration_nanos<S>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    duration(value, serializer, DurationUnit::Nanos)
+}
+
+/// Serialize a float with exactly `N` decimal places, e.g. `3.14` for
+/// `3.14159` with `N = 2`.
+///
+/// The value is rounded to the nearest representable output, with ties to
+/// even on the exact binary value, so `1.005` becomes `1.00`. A value which
+/// rounds to zero is written without sign, i.e. `-0.001` becomes `0.00`.
+/// NaN and the infinities are written as `NaN`, `inf` and `-inf`.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Order {
+///     #[serde(serialize_with = "serde_url_params::helpers::fixed_decimals::<2, _, _>")]
+///     price: f64,
+/// }
+///
+/// let order = Order { price: 9.5 };
+/// assert_eq!(serde_url_params::to_string(&order).unwrap(), "price=9.50");
+/// ```
+pub fn fixed_decimals<const N: usize, T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Copy + Into<f64>,
+    S: Serializer,
+{
+    let formatted = format!("{:.*}", N, (*value).into());
+    match formatted.strip_prefix('-') {
+        Some(unsigned) if unsigned.bytes().all(|b| b == b'0' || b == b'.') => {
+            serializer.serialize_str(unsigned)
+        }
+        _ => serializer.serialize_str(&formatted),
+    }
+}
+
+/// Serialize a `time::OffsetDateTime` as RFC 3339 timestamp, e.g.
+/// `2024-03-05T14:30:00+02:00`.
+///
+/// The timestamp is percent-encoded like any other string, so the colons
+/// become `%3A` and the `+` of the offset becomes `%2B`, i.e. it is not
+/// mistaken for an encoded space. This helper is only available with the
+/// `time` feature.
+///
+/// ```rust
+/// # #[cfg(feature = "time")]
+/// # {
+/// use serde::Serialize;
+/// use time::OffsetDateTime;
+///
+/// #[derive(Serialize)]
+/// struct Event {
+///     #[serde(serialize_with = "serde_url_params::helpers::time_rfc3339")]
+///     at: OffsetDateTime,
+/// }
+///
+/// let event = Event {
+///     at: OffsetDateTime::UNIX_EPOCH,
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&event).unwrap(),
+///     "at=1970-01-01T00%3A00%3A00Z"
+/// );
+/// # }
+/// ```
+#[cfg(feature = "time")]
+pub fn time_rfc3339<S>(value: &time::OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let formatted = value
+        .format(&time::format_description::well_known::Rfc3339)
+        .map_err(serde::ser::Error::custom)?;
+    serializer.serialize_str(&formatted)
+}
+
+/// Serialize a `bitflags` value as a single comma-separated list of the
+/// names of its set flags, e.g. `READ%2CWRITE`.
+///
+/// The names are written as declared, in declaration order; set bits without
+/// a name are omitted. Like [`comma_separated`], the joined value is
+/// percent-encoded as a whole, so the commas are written as `%2C` unless
+/// skipped by [`Config::encode_skip`](../config/struct.Config.html#method.encode_skip).
+/// This helper is only available with the `bitflags` feature.
+///
+/// ```rust
+/// # #[cfg(feature = "bitflags")]
+/// # {
+/// use serde::Serialize;
+///
+/// bitflags::bitflags! {
+///     struct Permissions: u8 {
+///         const READ = 1;
+///         const WRITE = 2;
+///         const EXECUTE = 4;
+///     }
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Grant {
+///     #[serde(serialize_with = "serde_url_params::helpers::bitflag_names")]
+///     perms: Permissions,
+/// }
+///
+/// let grant = Grant {
+///     perms: Permissions::READ | Permissions::WRITE,
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&grant).unwrap(),
+///     "perms=READ%2CWRITE"
+/// );
+/// # }
+/// ```
+#[cfg(feature = "bitflags")]
+pub fn bitflag_names<T, S>(flags: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: bitflags::Flags,
+    S: Serializer,
+{
+    let mut joined = String::new();
+    for (i, (name, _)) in flags.iter_names().enumerate() {
+        if i > 0 {
+            joined.push(',');
+        }
+        joined.push_str(name);
+    }
+    serializer.serialize_str(&joined)
+}
+
+/// Serialize a collection as a single value with elements joined by `D`.
+///
+/// Each element is formatted via `Display`. The joined string is
+/// percent-encoded once as a whole, so the delimiter is encoded as well if
+/// necessary. For the common delimiters, use the presets
+/// [`comma_separated`], [`space_separated`], [`pipe_separated`] and
+/// [`semicolon_separated`].
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     #[serde(serialize_with = "serde_url_params::helpers::delimited::<'/', _, _, _>")]
+///     path: Vec<&'static str>,
+/// }
+///
+/// let params = Params {
+///     path: vec!["a", "b"],
+/// };
+/// assert_eq!(serde_url_params::to_string(&params).unwrap(), "path=a%2Fb");
+/// ```
+pub fn delimited<'a, const D: char, C, T, S>(
+    values: &'a C,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = T>,
+    T: fmt::Display,
+    S: Serializer,
+{
+    use fmt::Write;
+    let mut joined = String::new();
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            joined.push(D);
+        }
+        write!(joined, "{}", value).map_err(serde::ser::Error::custom)?;
+    }
+    serializer.serialize_str(&joined)
+}
+
+/// Serialize a collection as a single comma-separated value, e.g. `a%2Cb`.
+///
+/// See [`delimited`].
+pub fn comma_separated<'a, C, T, S>(values: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = T>,
+    T: fmt::Display,
+    S: Serializer,
+{
+    delimited::<',', _, _, _>(values, serializer)
+}
+
+/// Serialize a collection as a single space-separated value, e.g. `a+b`.
+///
+/// See [`delimited`].
+pub fn space_separated<'a, C, T, S>(values: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = T>,
+    T: fmt::Display,
+    S: Serializer,
+{
+    delimited::<' ', _, _, _>(values, serializer)
+}
+
+/// Serialize a collection as a single pipe-separated value, e.g. `a%7Cb`.
+///
+/// See [`delimited`].
+pub fn pipe_separated<'a, C, T, S>(values: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = T>,
+    T: fmt::Display,
+    S: Serializer,
+{
+    delimited::<'|', _, _, _>(values, serializer)
+}
+
+/// Serialize a collection as a single semicolon-separated value, e.g.
+/// `a%3Bb`.
+///
+/// See [`delimited`].
+pub fn semicolon_separated<'a, C, T, S>(values: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = T>,
+    T: fmt::Display,
+    S: Serializer,
+{
+    delimited::<';', _, _, _>(values, serializer)
+}
+
+/// Serialize an optional collection as a single comma-separated value, or
+/// omit it if `None`.
+///
+/// An empty collection is written with an empty value, so `None` and
+/// `Some(vec![])` are distinguishable. See [`comma_separated`].
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     #[serde(serialize_with = "serde_url_params::helpers::opt_comma_separated")]
+///     tags: Option<Vec<&'static str>>,
+/// }
+///
+/// let search = Search { tags: None };
+/// assert_eq!(serde_url_params::to_string(&search).unwrap(), "");
+/// let search = Search { tags: Some(vec![]) };
+/// assert_eq!(serde_url_params::to_string(&search).unwrap(), "tags=");
+/// let search = Search {
+///     tags: Some(vec!["a", "b"]),
+/// };
+/// assert_eq!(serde_url_params::to_string(&search).unwrap(), "tags=a%2Cb");
+/// ```
+pub fn opt_comma_separated<'a, C, T, S>(
+    values: &'a Option<C>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    &'a C: IntoIterator<Item = T>,
+    T: fmt::Display,
+    S: Serializer,
+{
+    match values {
+        Some(values) => comma_separated(values, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Collection serialized as a single comma-separated value.
+///
+/// This is the wrapper type equivalent of [`comma_separated`].
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::helpers::{CommaSeparated, SpaceSeparated};
+///
+/// #[derive(Serialize)]
+/// struct Authorize {
+///     scope: SpaceSeparated<Vec<&'static str>>,
+///     ids: CommaSeparated<[u32; 2]>,
+/// }
+///
+/// let params = Authorize {
+///     scope: SpaceSeparated(vec!["openid", "profile"]),
+///     ids: CommaSeparated([1, 2]),
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "scope=openid+profile&ids=1%2C2"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CommaSeparated<C>(pub C);
+
+impl<C> From<C> for CommaSeparated<C> {
+    fn from(values: C) -> Self {
+        CommaSeparated(values)
+    }
+}
+
+impl<C> Serialize for CommaSeparated<C>
+where
+    for<'a> &'a C: IntoIterator,
+    for<'a> <&'a C as IntoIterator>::Item: fmt::Display,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        comma_separated(&self.0, serializer)
+    }
+}
+
+/// Collection serialized as a single space-separated value.
+///
+/// This is the wrapper type equivalent of [`space_separated`], see
+/// [`CommaSeparated`] for an example.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SpaceSeparated<C>(pub C);
+
+impl<C> From<C> for SpaceSeparated<C> {
+    fn from(values: C) -> Self {
+        SpaceSeparated(values)
+    }
+}
+
+impl<C> Serialize for SpaceSeparated<C>
+where
+    for<'a> &'a C: IntoIterator,
+    for<'a> <&'a C as IntoIterator>::Item: fmt::Display,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        space_separated(&self.0, serializer)
+    }
+}
+
+/// Serialize a unit variant as the string mapped to its name by `table`.
+///
+/// The table maps variant names (after `#[serde(rename)]`) to the output, so
+/// the same enum can be rendered differently per field, e.g. as numbers or
+/// localized strings. Serialization fails for a variant missing in the table.
+/// Since a table cannot be passed via `serialize_with`, wrap the helper in a
+/// function:
+///
+/// ```rust
+/// use serde::{Serialize, Serializer};
+/// use serde_url_params::helpers::map_variant;
+///
+/// #[derive(Serialize)]
+/// enum Weekday {
+///     Mon,
+///     Tue,
+/// }
+///
+/// fn weekday_number<S: Serializer>(day: &Weekday, serializer: S) -> Result<S::Ok, S::Error> {
+///     map_variant(day, serializer, &[("Mon", "1"), ("Tue", "2")])
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     #[serde(serialize_with = "weekday_number")]
+///     day: Weekday,
+/// }
+///
+/// let params = Params { day: Weekday::Tue };
+/// assert_eq!(serde_url_params::to_string(&params).unwrap(), "day=2");
+/// ```
+pub fn map_variant<T, S>(
+    value: &T,
+    serializer: S,
+    table: &[(&str, &str)],
+) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + Serialize,
+    S: Serializer,
+{
+    use serde::ser::Error;
+    let name = crate::ser::to_key_string(value).map_err(S::Error::custom)?;
+    match table.iter().find(|(variant, _)| *variant == name) {
+        Some((_, output)) => serializer.serialize_str(output),
+        None => Err(S::Error::custom(format_args!(
+            "no mapping for variant `{}`",
+            name
+        ))),
+    }
+}
+
+/// Serialize a collection as a sequence, with each element transformed by
+/// `element` before it is serialized.
+///
+/// Unlike `serialize_with` on the collection field, which applies to the
+/// collection as a whole, this formats each element on its own and keeps the
+/// sequence, so the elements are written according to the
+/// [`ArrayFormat`](../config/enum.ArrayFormat.html), e.g. as repeated keys.
+/// Since a closure cannot be passed via `serialize_with`, wrap the helper in a
+/// function:
+///
+/// ```rust
+/// use serde::{Serialize, Serializer};
+/// use serde_url_params::helpers::each;
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+///
+/// fn unix_seconds<S: Serializer>(
+///     times: &[SystemTime],
+///     serializer: S,
+/// ) -> Result<S::Ok, S::Error> {
+///     each(times, serializer, |time| {
+///         time.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+///     })
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     #[serde(serialize_with = "unix_seconds")]
+///     at: Vec<SystemTime>,
+/// }
+///
+/// let params = Params {
+///     at: vec![UNIX_EPOCH + Duration::from_secs(60), UNIX_EPOCH],
+/// };
+/// assert_eq!(serde_url_params::to_string(&params).unwrap(), "at=60&at=0");
+/// ```
+pub fn each<'a, C, T, F, R, S>(values: &'a C, serializer: S, element: F) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = T>,
+    F: FnMut(T) -> R,
+    R: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(values.into_iter().map(element))
+}
+
+/// Serialize a range as two parameters, whose keys are the key of the field
+/// followed by the given suffixes for the start resp. the end.
+///
+/// This works with any range type, e.g. `Range` and `RangeInclusive`. The
+/// end is written as is, so the API has to know whether it is inclusive. An
+/// unbounded side is omitted, e.g. `5..` only writes the start. For the
+/// common suffixes, use the presets [`range_underscore`] and
+/// [`range_brackets`]; other suffixes need a wrapper function:
+///
+/// ```rust
+/// use serde::{Serialize, Serializer};
+/// use serde_url_params::helpers::range;
+/// use std::ops::Range;
+///
+/// fn min_max<S: Serializer>(value: &Range<u32>, serializer: S) -> Result<S::Ok, S::Error> {
+///     range(value, serializer, ("_min", "_max"))
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     #[serde(serialize_with = "min_max")]
+///     price: Range<u32>,
+/// }
+///
+/// let params = Params { price: 10..20 };
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "price_min=10&price_max=20"
+/// );
+/// ```
+///
+/// Other serializers see a map from the suffixes to the bounds.
+pub fn range<R, T, S>(value: &R, serializer: S, suffixes: (&str, &str)) -> Result<S::Ok, S::Error>
+where
+    R: RangeBounds<T>,
+    T: Serialize,
+    S: Serializer,
+{
+    let bound = |bound| match bound {
+        Bound::Included(value) | Bound::Excluded(value) => Some(value),
+        Bound::Unbounded => None,
+    };
+    let bounds = [
+        (suffixes.0, bound(value.start_bound())),
+        (suffixes.1, bound(value.end_bound())),
+    ];
+    serializer.serialize_newtype_struct(SUFFIXED, &Bounds(bounds))
+}
+
+/// Serialize a range as two parameters with the suffixes `_start` and
+/// `_end`, e.g. `r_start=1&r_end=5`.
+///
+/// See [`range`].
+pub fn range_underscore<R, T, S>(value: &R, serializer: S) -> Result<S::Ok, S::Error>
+where
+    R: RangeBounds<T>,
+    T: Serialize,
+    S: Serializer,
+{
+    range(value, serializer, ("_start", "_end"))
+}
+
+/// Serialize a range as two parameters with the suffixes `[start]` and
+/// `[end]`, e.g. `r[start]=1&r[end]=5`.
+///
+/// See [`range`].
+pub fn range_brackets<R, T, S>(value: &R, serializer: S) -> Result<S::Ok, S::Error>
+where
+    R: RangeBounds<T>,
+    T: Serialize,
+    S: Serializer,
+{
+    range(value, serializer, ("[start]", "[end]"))
+}
+
+/// Name of the newtype struct by which the serializer recognizes a map whose
+/// keys are suffixes of the current key.
+pub(crate) const SUFFIXED: &str = "$serde_url_params::Suffixed";
+
+/// The bounds of a range by key suffix; unbounded sides are `None`.
+struct Bounds<'a, T>([(&'a str, Option<&'a T>); 2]);
+
+impl<'a, T: Serialize> Serialize for Bounds<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let bounds = self
+            .0
+            .iter()
+            .filter_map(|&(suffix, bound)| Some((suffix, bound?)));
+        serializer.collect_map(bounds)
+    }
+}
+
+/// Name of the newtype struct by which the serializer recognizes [`Raw`].
+pub(crate) const RAW: &str = "$serde_url_params::Raw";
+
+/// Already encoded query fragment which is written verbatim.
+///
+/// The fragment, e.g. `a=1&b=2`, is inserted as additional parameters into
+/// the output; the key of the field holding it is ignored. An empty fragment
+/// emits nothing. Other serializers see a plain string.
+///
+/// **Warning:** the fragment is neither encoded nor validated. Never build it
+/// from untrusted input, since e.g. a `&` or `=` in it injects arbitrary
+/// parameters into the query. Keys in the fragment are also not subject to
+/// duplicate key detection.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::helpers::Raw;
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     q: &'static str,
+///     rest: Raw,
+/// }
+///
+/// let params = Params {
+///     q: "a b",
+///     rest: Raw(String::from("sig=x%2By&v=2")),
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "q=a+b&sig=x%2By&v=2"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Raw(pub String);
+
+impl From<String> for Raw {
+    fn from(fragment: String) -> Self {
+        Raw(fragment)
+    }
+}
+
+impl Serialize for Raw {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(RAW, &self.0)
+    }
+}
+
+/// Name of the newtype struct by which the serializer recognizes
+/// [`PreEncoded`].
+pub(crate) const PRE_ENCODED: &str = "$serde_url_params::PreEncoded";
+
+/// Already percent-encoded string value which is written verbatim.
+///
+/// Unlike [`Raw`], this is a value of a single parameter written under the
+/// field's key, just without encoding it again. This avoids double encoding
+/// values which were encoded elsewhere, e.g. signatures. Other serializers see
+/// a plain string.
+///
+/// **Warning:** the value is neither encoded nor validated. If it contains a
+/// `&` or `=`, it injects arbitrary parameters into the query, so never build
+/// it from untrusted input.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::helpers::PreEncoded;
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     sig: PreEncoded,
+///     q: &'static str,
+/// }
+///
+/// let params = Params {
+///     sig: PreEncoded(String::from("a%2Bb%3D")),
+///     q: "a%2Bb",
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "sig=a%2Bb%3D&q=a%252Bb"
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PreEncoded(pub String);
+
+impl From<String> for PreEncoded {
+    fn from(value: String) -> Self {
+        PreEncoded(value)
+    }
+}
+
+impl Serialize for PreEncoded {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(PRE_ENCODED, &self.0)
+    }
+}
+
+/// Name of the newtype struct by which the serializer recognizes
+/// [`Repeated`].
+pub(crate) const REPEATED: &str = "$serde_url_params::Repeated";
+
+/// Name of the newtype struct by which the serializer recognizes
+/// [`CommaList`].
+pub(crate) const COMMA_LIST: &str = "$serde_url_params::CommaList";
+
+/// Name of the newtype struct by which the serializer recognizes
+/// [`Bracketed`].
+pub(crate) const BRACKETED: &str = "$serde_url_params::Bracketed";
+
+/// Sequence written with repeated keys, `key=a&key=b`, regardless of the
+/// configured [`ArrayFormat`](../config/enum.ArrayFormat.html).
+///
+/// Together with [`CommaList`] and [`Bracketed`], this allows choosing the
+/// array format per field. Other serializers see the inner value.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::helpers::{Bracketed, CommaList, Repeated};
+///
+/// #[derive(Serialize)]
+/// struct Params {
+///     ids: CommaList<Vec<u32>>,
+///     tags: Repeated<Vec<&'static str>>,
+///     sort: Bracketed<Vec<&'static str>>,
+/// }
+///
+/// let params = Params {
+///     ids: CommaList(vec![1, 2]),
+///     tags: Repeated(vec!["a", "b"]),
+///     sort: Bracketed(vec!["name"]),
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "ids=1,2&tags=a&tags=b&sort[]=name"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Repeated<T>(pub T);
+
+impl<T> From<T> for Repeated<T> {
+    fn from(values: T) -> Self {
+        Repeated(values)
+    }
+}
+
+impl<T: Serialize> Serialize for Repeated<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(REPEATED, &self.0)
+    }
+}
+
+/// Sequence joined by commas into a single parameter, `key=a,b`, regardless
+/// of the configured [`ArrayFormat`](../config/enum.ArrayFormat.html).
+///
+/// Unlike [`CommaSeparated`], the elements are written like those of
+/// `ArrayFormat::Comma`, i.e. the commas are literal unless
+/// `Config::comma_literal(false)` is set. See [`Repeated`] for an example.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CommaList<T>(pub T);
+
+impl<T> From<T> for CommaList<T> {
+    fn from(values: T) -> Self {
+        CommaList(values)
+    }
+}
+
+impl<T: Serialize> Serialize for CommaList<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(COMMA_LIST, &self.0)
+    }
+}
+
+/// Sequence written with bracketed keys, `key[]=a&key[]=b`, regardless of
+/// the configured [`ArrayFormat`](../config/enum.ArrayFormat.html).
+///
+/// The brackets hold the configured `BracketContent`. See [`Repeated`] for
+/// an example.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bracketed<T>(pub T);
+
+impl<T> From<T> for Bracketed<T> {
+    fn from(values: T) -> Self {
+        Bracketed(values)
+    }
+}
+
+impl<T: Serialize> Serialize for Bracketed<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(BRACKETED, &self.0)
+    }
+}
+
+/// A single parameter `key=value`.
+///
+/// This allows to serialize a one-off parameter without defining a struct.
+/// Like [`KeyedSeq`], the parameter is emitted as map entry, so `KeyValue` is
+/// either serialized at top level or flattened into a struct via
+/// `#[serde(flatten)]`.
+///
+/// ```rust
+/// use serde_url_params::helpers::KeyValue;
+///
+/// assert_eq!(
+///     serde_url_params::to_string(&KeyValue("q", "rust")).unwrap(),
+///     "q=rust"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyValue<K, V>(pub K, pub V);
+
+impl<K: Serialize, V: Serialize> Serialize for KeyValue<K, V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.0, &self.1)?;
+        map.end()
+    }
+}
+
+/// Collection of key value pairs, e.g. `Vec<(String, String)>`, serialized as
+/// parameters.
+///
+/// The pairs are emitted as map entries, keeping repeated keys, so the
+/// wrapper is meant to be flattened into a struct via `#[serde(flatten)]`.
+/// This promotes the pairs to parameters of the struct, e.g. to merge dynamic
+/// parameters into a static struct. Without the wrapper, a `Vec` of pairs is
+/// a sequence of tuples.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::helpers::Flattened;
+///
+/// #[derive(Serialize)]
+/// struct Request {
+///     id: u32,
+///     #[serde(flatten)]
+///     extra: Flattened<Vec<(String, String)>>,
+/// }
+///
+/// let request = Request {
+///     id: 7,
+///     extra: Flattened(vec![(String::from("sort"), String::from("asc"))]),
+/// };
+/// assert_eq!(serde_url_params::to_string(&request).unwrap(), "id=7&sort=asc");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Flattened<C>(pub C);
+
+impl<C, K, V> Serialize for Flattened<C>
+where
+    for<'a> &'a C: IntoIterator<Item = &'a (K, V)>,
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        for (key, value) in &self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Parameters with keys derived from the elements of a slice.
+///
+/// Created by [`keyed_seq`].
+pub struct KeyedSeq<'a, T, K, V> {
+    items: &'a [T],
+    key: K,
+    value: V,
+}
+
+/// Serialize each element of `items` as a separate parameter, whose key is
+/// computed by `key` and whose value is computed by `value`.
+///
+/// The key closure must be able to derive a unique key from each element, i.e.
+/// the element has to expose the data the key is built from, typically an id
+/// field. The value has to serialize as a single value (or sequence of values);
+/// nested structs are not supported.
+///
+/// Since the parameters are emitted as map entries, `KeyedSeq` is either
+/// serialized at top level or flattened into a struct via
+/// `#[serde(flatten)]`.
+///
+/// ```rust
+/// use serde_url_params::helpers::keyed_seq;
+///
+/// struct Item {
+///     id: u32,
+///     name: &'static str,
+/// }
+///
+/// let items = [
+///     Item { id: 1, name: "foo" },
+///     Item { id: 7, name: "bar" },
+/// ];
+/// let params = keyed_seq(&items, |item| format!("name_{}", item.id), |item| item.name);
+/// assert_eq!(
+///     serde_url_params::to_string(&params).unwrap(),
+///     "name_1=foo&name_7=bar"
+/// );
+/// ```
+pub fn keyed_seq<'a, T, K, V, R>(items: &'a [T], key: K, value: V) -> KeyedSeq<'a, T, K, V>
+where
+    K: Fn(&'a T) -> String,
+    V: Fn(&'a T) -> R,
+    R: Serialize,
+{
+    KeyedSeq { items, key, value }
+}
+
+impl<'a, T, K, V, R> Serialize for KeyedSeq<'a, T, K, V>
+where
+    K: Fn(&'a T) -> String,
+    V: Fn(&'a T) -> R,
+    R: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.items.len()))?;
+        for item in self.items {
+            map.serialize_entry(&(self.key)(item), &(self.value)(item))?;
+        }
+        map.end()
+    }
+}
+
+/// Serialize each element of a sequence of key-value structs as a separate
+/// parameter, named by the element's `key` field and valued by its `value`
+/// field.
+///
+/// The elements must serialize as structs with a `key` field declared before
+/// a `value` field; other fields are ignored. The key has to serialize as a
+/// single value, e.g. a string or an integer. The parameters are emitted as
+/// map entries, so the field holding the sequence contributes no key of its
+/// own.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Pair {
+///     key: String,
+///     value: u32,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     #[serde(serialize_with = "serde_url_params::helpers::kv_seq")]
+///     filters: Vec<Pair>,
+/// }
+///
+/// let search = Search {
+///     filters: vec![
+///         Pair { key: "a".into(), value: 1 },
+///         Pair { key: "b".into(), value: 2 },
+///     ],
+/// };
+/// assert_eq!(serde_url_params::to_string(&search).unwrap(), "a=1&b=2");
+/// ```
+pub fn kv_seq<'a, C, T, S>(items: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+where
+    C: ?Sized,
+    &'a C: IntoIterator<Item = &'a T>,
+    T: 'a + Serialize,
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(None)?;
+    for item in items {
+        item.serialize(KeyValueFields { map: &mut map })?;
+    }
+    map.end()
+}
+
+/// Serializer of a single element of [`kv_seq`], forwarding its `key` and
+/// `value` fields to the entries of the enclosing map.
+struct KeyValueFields<'a, M> {
+    map: &'a mut M,
+}
+
+macro_rules! reject_non_struct {
+    ($($method:ident($($arg:ty),*) -> $ret:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ret, M::Error> {
+                Err(M::Error::custom(
+                    "expected a struct with `key` and `value` fields",
+                ))
+            }
+        )*
+    };
+}
+
+impl<'a, M: SerializeMap> Serializer for KeyValueFields<'a, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = Impossible<(), M::Error>;
+    type SerializeStruct = KeyValueStruct<'a, M>;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    reject_non_struct! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_none() -> ();
+        serialize_unit() -> ();
+        serialize_unit_struct(&'static str) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), M::Error> {
+        Err(M::Error::custom(
+            "expected a struct with `key` and `value` fields",
+        ))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, M::Error> {
+        Ok(KeyValueStruct {
+            map: self.map,
+            has_key: false,
+            has_value: false,
+        })
+    }
+}
+
+struct KeyValueStruct<'a, M> {
+    map: &'a mut M,
+    has_key: bool,
+    has_value: bool,
+}
+
+impl<'a, M: SerializeMap> SerializeStruct for KeyValueStruct<'a, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error> {
+        match key {
+            "key" if !self.has_key => {
+                self.has_key = true;
+                self.map.serialize_key(value)
+            }
+            "value" if self.has_key && !self.has_value => {
+                self.has_value = true;
+                self.map.serialize_value(value)
+            }
+            "key" | "value" => Err(M::Error::custom(
+                "the `key` field must be followed by exactly one `value` field",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        if self.has_key && self.has_value {
+            Ok(())
+        } else {
+            Err(M::Error::custom(
+                "expected a struct with `key` and `value` fields",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        keyed_seq, CommaSeparated, KeyValue, KeyedSeq, NullIfNone, OmitIfNone, PreEncoded, Raw,
+        SpaceSeparated,
+    };
+    use crate::to_string;
+    use serde::Serialize;
+
+    #[test]
+    fn test_option_as_null() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(with = "super::option_as_null")]
+            email: Option<&'static str>,
+            #[serde(with = "super::option_as_null")]
+            age: Option<u32>,
+            name: Option<&'static str>,
+        }
+        let params = Params {
+            email: None,
+            age: Some(42),
+            name: None,
+        };
+        assert_eq!(to_string(&params).unwrap(), "email=null&age=42");
+        let params = Params {
+            email: Some("a@b.c"),
+            age: None,
+            name: Some("x"),
+        };
+        assert_eq!(to_string(&params).unwrap(), "email=a%40b.c&age=null&name=x");
+    }
+
+    #[test]
+    fn test_key_value() {
+        assert_eq!(to_string(&KeyValue("q", "a b")).unwrap(), "q=a+b");
+        assert_eq!(to_string(&KeyValue(1, vec![2, 3])).unwrap(), "1=2&1=3");
+        assert_eq!(to_string(&KeyValue("page", None::<u32>)).unwrap(), "");
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            id: u32,
+            #[serde(flatten)]
+            extra: KeyValue<String, bool>,
+        }
+        let params = Params {
+            id: 1,
+            extra: KeyValue(String::from("debug"), true),
+        };
+        assert_eq!(to_string(&params).unwrap(), "id=1&debug=true");
+    }
+
+    #[test]
+    fn test_keyed_seq_flattened() {
+        struct Item {
+            id: u32,
+            name: &'static str,
+        }
+        type Names<'a> = KeyedSeq<'a, Item, fn(&Item) -> String, fn(&Item) -> &str>;
+        #[derive(Serialize)]
+        struct Params<'a> {
+            q: &'static str,
+            #[serde(flatten)]
+            names: Names<'a>,
+        }
+        let items = vec![Item { id: 3, name: "a b" }, Item { id: 4, name: "c" }];
+        let params = Params {
+            q: "x",
+            names: keyed_seq(&items, |item| format!("name_{}", item.id), |item| item.name),
+        };
+        assert_eq!(to_string(&params).unwrap(), "q=x&name_3=a+b&name_4=c");
+    }
+
+    #[test]
+    fn test_nested_query() {
+        use crate::{Config, EncodeSet};
+        use serde::Serializer;
+
+        #[derive(Debug, Serialize)]
+        struct Inner {
+            a: u32,
+            b: &'static str,
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            id: u32,
+            #[serde(serialize_with = "super::nested_query")]
+            callback: Inner,
+            #[serde(serialize_with = "nested_with_config")]
+            configured: Inner,
+        }
+        fn config() -> Config {
+            Config::new().encode_set(EncodeSet::Unreserved)
+        }
+        fn nested_with_config<S: Serializer>(
+            value: &Inner,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            super::nested_query_with(value, serializer, &config())
+        }
+        let params = Params {
+            id: 1,
+            callback: Inner { a: 1, b: "x y" },
+            configured: Inner { a: 1, b: "x y" },
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "id=1&callback=a%3D1%26b%3Dx%2By&configured=a%3D1%26b%3Dx%2520y"
+        );
+        // the outer configuration does not apply to the nested query
+        assert_eq!(
+            config().to_string(&params).unwrap(),
+            "id=1&callback=a%3D1%26b%3Dx%2By&configured=a%3D1%26b%3Dx%2520y"
+        );
+    }
+
+    #[test]
+    fn test_delimited_presets() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "super::comma_separated")]
+            comma: Vec<&'static str>,
+            #[serde(serialize_with = "super::space_separated")]
+            space: Vec<u32>,
+            #[serde(serialize_with = "super::pipe_separated")]
+            pipe: [&'static str; 2],
+            #[serde(serialize_with = "super::semicolon_separated")]
+            semicolon: std::collections::BTreeSet<char>,
+            #[serde(serialize_with = "super::comma_separated")]
+            empty: Vec<&'static str>,
+        }
+        let params = Params {
+            comma: vec!["openid", "profile"],
+            space: vec![1, 2, 3],
+            pipe: ["a&b", "c"],
+            semicolon: vec!['y', 'x'].into_iter().collect(),
+            empty: vec![],
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "comma=openid%2Cprofile&space=1+2+3&pipe=a%26b%7Cc&semicolon=x%3By&empty="
+        );
+    }
+
+    #[test]
+    fn test_opt_comma_separated() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "super::opt_comma_separated")]
+            ids: Option<Vec<u32>>,
+            page: u32,
+        }
+        let cases = [
+            (None, "page=1"),
+            (Some(vec![]), "ids=&page=1"),
+            (Some(vec![7]), "ids=7&page=1"),
+            (Some(vec![1, 2, 3]), "ids=1%2C2%2C3&page=1"),
+        ];
+        for (ids, expected) in cases.iter() {
+            let params = Params {
+                ids: ids.clone(),
+                page: 1,
+            };
+            assert_eq!(to_string(&params).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_mixed_none_wrappers() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: OmitIfNone<u32>,
+            b: NullIfNone<u32>,
+            c: OmitIfNone<&'static str>,
+            d: NullIfNone<&'static str>,
+        }
+        let params = Params {
+            a: None.into(),
+            b: None.into(),
+            c: Some("x").into(),
+            d: Some("y").into(),
+        };
+        assert_eq!(to_string(&params).unwrap(), "b=null&c=x&d=y");
+        let params = Params {
+            a: Some(1).into(),
+            b: Some(2).into(),
+            c: None.into(),
+            d: None.into(),
+        };
+        assert_eq!(to_string(&params).unwrap(), "a=1&b=2&d=null");
+    }
+
+    #[test]
+    fn test_separated_wrappers() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            tags: CommaSeparated<Vec<String>>,
+            scope: SpaceSeparated<std::collections::BTreeSet<&'static str>>,
+            empty: CommaSeparated<Vec<u32>>,
+            ids: Option<CommaSeparated<Vec<u32>>>,
+        }
+        let params = Params {
+            tags: vec![String::from("a b"), String::from("c")].into(),
+            scope: vec!["write", "read"]
+                .into_iter()
+                .collect::<std::collections::BTreeSet<_>>()
+                .into(),
+            empty: CommaSeparated(vec![]),
+            ids: None,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "tags=a+b%2Cc&scope=read+write&empty="
+        );
+    }
+
+    #[test]
+    fn test_raw() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            pre: Raw,
+            q: &'static str,
+            post: Option<Raw>,
+            empty: Raw,
+        }
+        let params = Params {
+            pre: Raw(String::from("a=%20&b")),
+            q: "x&y",
+            post: Some(Raw(String::from("c=1"))),
+            empty: Raw::default(),
+        };
+        assert_eq!(to_string(&params).unwrap(), "a=%20&b&q=x%26y&c=1");
+        let params = Params {
+            pre: Raw::default(),
+            q: "x",
+            post: None,
+            empty: Raw::default(),
+        };
+        assert_eq!(to_string(&params).unwrap(), "q=x");
+    }
+
+    #[test]
+    fn test_map_variant() {
+        use serde::Serializer;
+
+        #[derive(Debug, Serialize)]
+        enum Day {
+            Sun,
+            #[serde(rename = "monday")]
+            Mon,
+            Sat,
+        }
+        const NUMBERS: &[(&str, &str)] = &[("Sun", "0"), ("monday", "1")];
+        fn number<S: Serializer>(day: &Day, serializer: S) -> Result<S::Ok, S::Error> {
+            super::map_variant(day, serializer, NUMBERS)
+        }
+        fn german<S: Serializer>(day: &Day, serializer: S) -> Result<S::Ok, S::Error> {
+            super::map_variant(day, serializer, &[("Sun", "Sonntag"), ("monday", "Montag")])
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "number")]
+            day: Day,
+            #[serde(serialize_with = "german")]
+            name: Day,
+            raw: Day,
+        }
+        let params = Params {
+            day: Day::Mon,
+            name: Day::Sun,
+            raw: Day::Mon,
+        };
+        assert_eq!(to_string(&params).unwrap(), "day=1&name=Sonntag&raw=monday");
+        let params = Params {
+            day: Day::Sat,
+            name: Day::Sun,
+            raw: Day::Sat,
+        };
+        assert_eq!(
+            to_string(&params).unwrap_err().to_string(),
+            "no mapping for variant `Sat` (at day)"
+        );
+    }
+
+    #[test]
+    fn test_display_str() {
+        use std::fmt;
+
+        /// A decimal number, serialized through a float like e.g. the
+        /// `serde-float` feature of `rust_decimal` does.
+        #[derive(Debug)]
+        struct Decimal {
+            mantissa: i128,
+            scale: u32,
+        }
+
+        impl fmt::Display for Decimal {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let divisor = 10i128.pow(self.scale);
+                let sign = if self.mantissa < 0 { "-" } else { "" };
+                let abs = self.mantissa.abs();
+                write!(f, "{}{}", sign, abs / divisor)?;
+                if self.scale > 0 {
+                    write!(f, ".{:0width$}", abs % divisor, width = self.scale as usize)?;
+                }
+                Ok(())
+            }
+        }
+
+        impl Serialize for Decimal {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let value = self.mantissa as f64 / 10f64.powi(self.scale as i32);
+                serializer.serialize_f64(value)
+            }
+        }
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            lossy: Decimal,
+            #[serde(serialize_with = "super::display_str")]
+            exact: Decimal,
+            #[serde(serialize_with = "super::display_str")]
+            negative: Decimal,
+        }
+        let params = Params {
+            lossy: Decimal {
+                mantissa: 12_345_678_901_234_567_891,
+                scale: 2,
+            },
+            exact: Decimal {
+                mantissa: 12_345_678_901_234_567_891,
+                scale: 2,
+            },
+            negative: Decimal {
+                mantissa: -5,
+                scale: 3,
+            },
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "lossy=123456789012345660&exact=123456789012345678.91&negative=-0.005"
+        );
+    }
+
+    #[test]
+    fn test_iso8601_duration() {
+        use std::time::Duration;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "super::iso8601_duration")]
+            d: Duration,
+        }
+        let cases = [
+            (Duration::from_secs(0), "PT0S"),
+            (Duration::from_millis(500), "PT0.5S"),
+            (Duration::from_nanos(1), "PT0.000000001S"),
+            (Duration::from_millis(61_250), "PT1M1.25S"),
+            (Duration::from_secs(3600), "PT1H"),
+            (Duration::from_secs(26 * 3600 + 5), "PT26H5S"),
+            (Duration::from_secs(7320), "PT2H2M"),
+        ];
+        for (d, expected) in cases.iter() {
+            assert_eq!(
+                to_string(&Params { d: *d }).unwrap(),
+                format!("d={}", expected)
+            );
+        }
+    }
+
+    #[cfg(feature = "bitflags")]
+    #[test]
+    #[allow(non_upper_case_globals)]
+    fn test_bitflag_names() {
+        use crate::Config;
+
+        bitflags::bitflags! {
+            #[derive(Debug)]
+            struct Permissions: u8 {
+                const read = 1;
+                const write = 2;
+                const admin = 4;
+            }
+        }
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "super::bitflag_names")]
+            perms: Permissions,
+        }
+        let params = Params {
+            perms: Permissions::write | Permissions::read,
+        };
+        assert_eq!(to_string(&params).unwrap(), "perms=read%2Cwrite");
+        let config = Config::new().encode_skip(b",");
+        assert_eq!(config.to_string(&params).unwrap(), "perms=read,write");
+        let params = Params {
+            perms: Permissions::from_bits_retain(0b1100),
+        };
+        assert_eq!(config.to_string(&params).unwrap(), "perms=admin");
+        let params = Params {
+            perms: Permissions::empty(),
+        };
+        assert_eq!(config.to_string(&params).unwrap(), "perms=");
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_rfc3339() {
+        use time::{Date, Month, OffsetDateTime, UtcOffset};
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "super::time_rfc3339")]
+            at: OffsetDateTime,
+        }
+        let at = Date::from_calendar_date(2024, Month::March, 5)
+            .unwrap()
+            .with_hms_milli(14, 30, 0, 250)
+            .unwrap();
+        let params = Params {
+            at: at.assume_offset(UtcOffset::from_hms(2, 0, 0).unwrap()),
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "at=2024-03-05T14%3A30%3A00.25%2B02%3A00"
+        );
+        let params = Params {
+            at: at.assume_offset(UtcOffset::from_hms(-5, -30, 0).unwrap()),
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "at=2024-03-05T14%3A30%3A00.25-05%3A30"
+        );
+    }
+
+    #[test]
+    fn test_fixed_decimals() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "super::fixed_decimals::<2, _, _>")]
+            price: f64,
+            #[serde(serialize_with = "super::fixed_decimals::<0, _, _>")]
+            count: f32,
+            #[serde(serialize_with = "super::fixed_decimals::<3, _, _>")]
+            lat: f64,
+        }
+        let params = |price, count, lat| to_string(&Params { price, count, lat }).unwrap();
+        assert_eq!(
+            params(std::f64::consts::PI, 2.5, 1.0),
+            "price=3.14&count=2&lat=1.000"
+        );
+        assert_eq!(
+            params(2.675, 3.5, -12.3456),
+            "price=2.67&count=4&lat=-12.346"
+        );
+        assert_eq!(
+            params(-0.001, -0.0, -0.0004),
+            "price=0.00&count=0&lat=0.000"
+        );
+        assert_eq!(
+            params(-0.005001, -0.6, 1e6),
+            "price=-0.01&count=-1&lat=1000000.000"
+        );
+        assert_eq!(
+            params(f64::NAN, f32::INFINITY, f64::NEG_INFINITY),
+            "price=NaN&count=inf&lat=-inf"
+        );
+    }
+
+    #[test]
+    fn test_range() {
+        use crate::{Config, Order};
+        use std::ops::{Range, RangeFrom, RangeInclusive};
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            #[serde(serialize_with = "super::range_underscore")]
+            r: Range<u32>,
+            #[serde(serialize_with = "super::range_brackets")]
+            date: RangeInclusive<&'static str>,
+            #[serde(serialize_with = "super::range_underscore")]
+            from: RangeFrom<i32>,
+        }
+        let params = Params {
+            q: "x",
+            r: 1..5,
+            date: "2024-01-01"..="2024-12-31",
+            from: -3..,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "q=x&r_start=1&r_end=5&date[start]=2024-01-01&date[end]=2024-12-31&from_start=-3"
+        );
+        let config = Config::new().order(Order::Sorted);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "date[end]=2024-12-31&date[start]=2024-01-01&from_start=-3&q=x&r_end=5&r_start=1"
+        );
+        // the suffixes do not leak into other map entries
+        #[derive(Debug, Serialize)]
+        struct Flattened {
+            #[serde(serialize_with = "super::range_underscore")]
+            r: Range<u32>,
+            #[serde(flatten)]
+            extra: std::collections::BTreeMap<&'static str, u32>,
+        }
+        let params = Flattened {
+            r: 0..1,
+            extra: vec![("a", 2)].into_iter().collect(),
+        };
+        assert_eq!(to_string(&params).unwrap(), "r_start=0&r_end=1&a=2");
+    }
+
+    #[test]
+    fn test_each() {
+        use crate::{ArrayFormat, Config};
+        use serde::Serializer;
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        fn unix_seconds<S: Serializer>(
+            times: &[SystemTime],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            super::each(times, serializer, |time| {
+                let since = time.duration_since(UNIX_EPOCH).unwrap();
+                format!("{}.{:03}", since.as_secs(), since.subsec_millis())
+            })
+        }
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(serialize_with = "unix_seconds")]
+            at: Vec<SystemTime>,
+        }
+        let params = Params {
+            at: vec![
+                UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+                UNIX_EPOCH + Duration::from_secs(60),
+            ],
+        };
+        assert_eq!(to_string(&params).unwrap(), "at=1700000000.250&at=60.000");
+        let config = Config::new().array_format(ArrayFormat::Indexed);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "at[0]=1700000000.250&at[1]=60.000"
+        );
+        let params = Params { at: vec![] };
+        assert_eq!(to_string(&params).unwrap(), "");
+    }
+
+    #[test]
+    fn test_pre_encoded() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            sig: PreEncoded,
+            list: Vec<PreEncoded>,
+            opt: Option<PreEncoded>,
+        }
+        let params = Params {
+            sig: PreEncoded(String::from("x%20y+z")),
+            list: vec![PreEncoded(String::from("%2C")), PreEncoded::default()],
+            opt: None,
+        };
+        assert_eq!(to_string(&params).unwrap(), "sig=x%20y+z&list=%2C&list=");
+    }
+
+    #[test]
+    fn test_kv_seq() {
+        #[derive(Serialize)]
+        struct Pair {
+            key: &'static str,
+            value: u32,
+        }
+
+        #[derive(Serialize)]
+        struct Search {
+            page: u32,
+            #[serde(serialize_with = "super::kv_seq")]
+            filters: Vec<Pair>,
+        }
+
+        let search = Search {
+            page: 1,
+            filters: vec![Pair { key: "a", value: 1 }, Pair { key: "b", value: 2 }],
+        };
+        assert_eq!(to_string(&search).unwrap(), "page=1&a=1&b=2");
+
+        #[derive(Serialize)]
+        struct ValueFirst {
+            value: u32,
+            key: &'static str,
+        }
+
+        #[derive(Serialize)]
+        struct Invalid {
+            #[serde(serialize_with = "super::kv_seq")]
+            filters: Vec<ValueFirst>,
+        }
+
+        let invalid = Invalid {
+            filters: vec![ValueFirst { value: 1, key: "a" }],
+        };
+        assert!(to_string(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_flatten_or_marker() {
+        fn marker<S: serde::Serializer>(
+            value: &Option<Complex>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            super::flatten_or_marker(value, serializer, ("options", ""))
+        }
+
+        #[derive(Serialize)]
+        struct Complex {
+            depth: u32,
+            tags: Vec<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct Params {
+            q: &'static str,
+            #[serde(flatten, serialize_with = "marker")]
+            options: Option<Complex>,
+        }
+
+        let params = Params {
+            q: "rust",
+            options: None,
+        };
+        assert_eq!(to_string(&params).unwrap(), "q=rust&options=");
+
+        let params = Params {
+            q: "rust",
+            options: Some(Complex {
+                depth: 2,
+                tags: vec!["a", "b"],
+            }),
+        };
+        assert_eq!(to_string(&params).unwrap(), "q=rust&depth=2&tags=a&tags=b");
+    }
+
+    #[test]
+    fn test_array_format_wrappers() {
+        use super::{Bracketed, CommaList, Repeated};
+        use crate::{ArrayFormat, Config};
+
+        #[derive(Serialize)]
+        struct Params {
+            ids: CommaList<Vec<u32>>,
+            tags: Repeated<[&'static str; 2]>,
+            sort: Bracketed<Vec<&'static str>>,
+            pages: Vec<u32>,
+        }
+
+        let params = Params {
+            ids: CommaList(vec![1, 2]),
+            tags: Repeated(["a", "b"]),
+            sort: Bracketed(vec!["name", "date"]),
+            pages: vec![1, 2],
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "ids=1,2&tags=a&tags=b&sort[]=name&sort[]=date&pages=1&pages=2"
+        );
+        let config = Config::new().array_format(ArrayFormat::Indexed);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "ids=1,2&tags=a&tags=b&sort[]=name&sort[]=date&pages[0]=1&pages[1]=2"
+        );
+    }
+
+    #[test]
+    fn test_duration_units() {
+        use std::time::Duration;
+
+        #[derive(Serialize)]
+        struct Params {
+            #[serde(serialize_with = "super::duration_secs")]
+            s: Duration,
+            #[serde(serialize_with = "super::duration_millis")]
+            ms: Duration,
+            #[serde(serialize_with = "super::duration_micros")]
+            us: Duration,
+            #[serde(serialize_with = "super::duration_nanos")]
+            ns: Duration,
+        }
+
+        let value = Duration::from_millis(1500);
+        let params = Params {
+            s: value,
+            ms: value,
+            us: value,
+            ns: value,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "s=1&ms=1500&us=1500000&ns=1500000000"
+        );
+
+        let params = Params {
+            s: Duration::MAX,
+            ms: Duration::MAX,
+            us: Duration::ZERO,
+            ns: Duration::MAX,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "s=18446744073709551615&ms=18446744073709551615999&us=0\
+             &ns=18446744073709551615999999999"
+        );
+    }
+}
@@ -66,32 +66,103 @@
 //! not obvious how to serialize them into flat parameters list:
 //!
 //! * any simple top level value, since it does not have a parameter key, and
-//! * any nested struct, since it is not obvious how to flatten it,
-//! * any map, which is not flattened (i.e. annotated with `#[serde(flatten)]`).
+//! * any nested struct, which is not flattened (i.e. annotated with
+//!   `#[serde(flatten)]`), since it is not obvious how to flatten it.
+//!
+//! Maps are serialized with their keys as parameter keys, so e.g. a top level
+//! `HashMap<String, String>` becomes `k1=v1&k2=v2`, which allows to build
+//! fully dynamic queries. A map held by a field is flattened the same way,
+//! i.e. the key of the field is not written. The order of a `HashMap` is
+//! unspecified unless [`Config::sort_map_keys`] or [`Config::order`] is used.
 //!
 //! Further, any string is automatically URL encoded (or more precisely,
 //! percentage encoded). Elements in `Vec`s are serialized as repeated
-//! `key=value` pairs, where key is the field holding the vector. Newtype
+//! `key=value` pairs, where key is the field holding the vector. The same
+//! applies to sets: `BTreeSet` keeps its natural order, whereas the order of
+//! `HashSet` is unspecified unless [`Config::sort_seq_values`] is used. Newtype
 //! variants and variant structs are flattened by omitting the name of the
-//! variant resp. struct.
+//! variant resp. struct. In particular, this applies to `Result`: both
+//! `Ok(v)` and `Err(v)` serialize as `key=v` and are thus indistinguishable in
+//! the output. Use [`Config::newtype_variant_format`] to keep the variant name
+//! as a prefix of the value instead.
+//!
+//! Enums annotated with `#[serde(untagged)]` serialize exactly like the
+//! content of the active variant: a scalar variant produces a single
+//! `key=value` pair, while a struct variant is treated like any other struct,
+//! i.e. it is only supported at top level (or when flattened).
+//!
+//! Paths (`Path` and `PathBuf`) are serialized as their UTF-8 string
+//! representation, percent-encoded like any other string, e.g. `/tmp/a b`
+//! becomes `%2Ftmp%2Fa+b`. A path which is not valid UTF-8 fails to serialize
+//! instead of being converted lossily.
+//!
+//! The reverse direction is provided by
+//! [`serde_url_params::from_str`][from_str], which parses URL parameters into
+//! any type implementing Serde's `Deserialize` trait. Repeated keys are
+//! collected into sequences, such that e.g. `filter=a&filter=b` deserializes
+//! into a `Vec` field `filter`.
 //!
 //! [to_string]: ser/fn.to_string.html
 //! [to_vec]: ser/fn.to_vec.html
 //! [to_writer]: ser/fn.to_writer.html
+//! [from_str]: de/fn.from_str.html
 
 #![deny(missing_docs)]
 
+#[doc(inline)]
+pub use self::config::{
+    ArrayFormat, BoolFormat, BracketContent, BytesFormat, Config, EmptyOutputPolicy, EncodeSet,
+    KeyCase, KeyCollision, NewlinePolicy, NewtypeVariantFormat, Order, TopLevelPolicy, UnitFormat,
+    UnitVariantFormat,
+};
+#[cfg(feature = "unstable")]
+#[doc(inline)]
+pub use self::config::{Form, StaticEncodeSet, StaticEncoder, Unreserved};
+#[doc(inline)]
+pub use self::de::{from_bytes, from_str, Deserializer};
+#[doc(inline)]
+pub use self::diff::DiffSerialize;
 #[doc(inline)]
 pub use self::error::{Error, Result};
+pub use self::merge::{merge_queries, MergePolicy};
+pub use self::params::QueryParams;
+#[cfg(feature = "tokio")]
 #[doc(inline)]
-pub use self::ser::{to_string, to_vec, to_writer, Serializer};
+pub use self::ser::to_async_writer;
+#[cfg(feature = "compact_str")]
+#[doc(inline)]
+pub use self::ser::to_compact_string;
+#[cfg(feature = "smol_str")]
+#[doc(inline)]
+pub use self::ser::to_smolstr;
+#[cfg(feature = "unstable")]
+#[doc(inline)]
+pub use self::ser::to_string_with_spans;
+#[doc(inline)]
+pub use self::ser::{
+    serialized_len, to_encoded_pairs, to_form_urlencoded, to_map, to_pairs, to_string,
+    to_string_explained, to_string_if, to_string_with_report, to_vec, to_writer, Explanation,
+    FieldOutcome, Serializer, Warning,
+};
+#[doc(inline)]
+pub use self::writer::QueryWriter;
 
+pub mod config;
+pub mod de;
+pub mod diff;
 pub mod error;
+pub mod helpers;
+pub mod merge;
+pub mod params;
+pub mod prelude;
 pub mod ser;
+pub mod writer;
 
 #[cfg(test)]
 mod tests {
-    use super::to_string;
+    use super::{
+        to_string, to_writer, ArrayFormat, BoolFormat, Config, NewtypeVariantFormat, UnitFormat,
+    };
     use serde::Serialize;
 
     #[derive(Debug, Serialize)]
@@ -283,6 +354,56 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_flattened_struct_with_seq() {
+        #[derive(Serialize, Debug)]
+        pub struct Filter {
+            tags: Vec<&'static str>,
+            ids: Vec<u32>,
+            name: &'static str,
+        }
+
+        #[derive(Serialize, Debug)]
+        pub struct Params {
+            page: u32,
+            #[serde(flatten)]
+            filter: Filter,
+            per_page: u32,
+        }
+
+        let params = Params {
+            page: 1,
+            filter: Filter {
+                tags: vec!["a", "b"],
+                ids: vec![],
+                name: "x",
+            },
+            per_page: 10,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "page=1&tags=a&tags=b&name=x&per_page=10"
+        );
+
+        // The keys are set via `serialize_key` of the flattened map, so the
+        // array formats have to pick them up from there as well.
+        let config = Config::new().array_format(ArrayFormat::Indexed);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "page=1&tags[0]=a&tags[1]=b&name=x&per_page=10"
+        );
+        let config = Config::new().array_format(ArrayFormat::Comma);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "page=1&tags=a,b&name=x&per_page=10"
+        );
+        let config = Config::new().strict(true);
+        assert_eq!(
+            config.to_string(&params).unwrap_err().to_string(),
+            "unsupported empty sequence in strict mode (at ids)"
+        );
+    }
+
     #[test]
     fn test_seq_of_struct() {
         #[derive(Serialize, Debug)]
@@ -315,4 +436,2548 @@ mod tests {
             "real=0&imag=1&real=1&imag=0"
         );
     }
+
+    #[test]
+    fn test_bool_format_flag() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            include_deleted: bool,
+            q: &'static str,
+            archived: bool,
+        }
+        let config = Config::new().bool_format(BoolFormat::FlagIfTrue);
+        let params = Params {
+            include_deleted: true,
+            q: "rust",
+            archived: false,
+        };
+        assert_eq!(config.to_string(&params).unwrap(), "include_deleted&q=rust");
+
+        let params = Params {
+            include_deleted: false,
+            q: "rust",
+            archived: true,
+        };
+        assert_eq!(config.to_string(&params).unwrap(), "q=rust&archived");
+
+        let params = Params {
+            include_deleted: false,
+            q: "rust",
+            archived: false,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "include_deleted=false&q=rust&archived=false"
+        );
+    }
+
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_spans() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            tag: Vec<&'static str>,
+            flag: bool,
+        }
+        let params = Params {
+            q: "a b",
+            tag: vec!["x", "yz"],
+            flag: true,
+        };
+        let (s, spans) = super::to_string_with_spans(&params).unwrap();
+        assert_eq!(s, "q=a+b&tag=x&tag=yz&flag=true");
+        let keys: Vec<_> = spans.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["q", "tag", "tag", "flag"]);
+        let parts: Vec<_> = spans.iter().map(|(_, r)| &s[r.clone()]).collect();
+        assert_eq!(parts, ["q=a+b", "tag=x", "tag=yz", "flag=true"]);
+    }
+
+    #[test]
+    fn test_newtype_variant_tagged() {
+        #[derive(Debug, Serialize)]
+        enum Sort {
+            #[serde(rename = "by name")]
+            ByName(&'static str),
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            results: Vec<::std::result::Result<&'static str, &'static str>>,
+            sort: Sort,
+        }
+        let params = Params {
+            results: vec![Ok("pass"), Err("fail")],
+            sort: Sort::ByName("a b"),
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "results=pass&results=fail&sort=a+b"
+        );
+        let config = Config::new().newtype_variant_format(NewtypeVariantFormat::TaggedColon);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "results=Ok:pass&results=Err:fail&sort=by+name:a+b"
+        );
+        let config = config.variant_separator("~");
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "results=Ok~pass&results=Err~fail&sort=by+name~a+b"
+        );
+    }
+
+    #[test]
+    fn test_newtype_variant_tagged_numeric() {
+        #[derive(Debug, Serialize)]
+        enum Limit {
+            #[serde(rename = "max&")]
+            Max(u32),
+            Ratio(f64),
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            limits: Vec<Limit>,
+            opt: Option<Limit>,
+        }
+        let params = Params {
+            limits: vec![Limit::Max(10), Limit::Ratio(-0.5)],
+            opt: Some(Limit::Max(0)),
+        };
+        let config = Config::new().newtype_variant_format(NewtypeVariantFormat::TaggedColon);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "limits=max%26:10&limits=Ratio:-0.5&opt=max%26:0"
+        );
+    }
+
+    #[test]
+    fn test_to_writer_flushes() {
+        struct Recorder {
+            data: Vec<u8>,
+            flushed: bool,
+        }
+        impl std::io::Write for Recorder {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.flushed = false;
+                self.data.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                self.flushed = true;
+                Ok(())
+            }
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            id: u32,
+        }
+        let mut writer = Recorder {
+            data: Vec::new(),
+            flushed: false,
+        };
+        to_writer(&mut writer, &Params { id: 1 }).unwrap();
+        assert!(writer.flushed);
+        assert_eq!(writer.data, b"id=1");
+
+        let mut writer = std::io::BufWriter::new(Vec::new());
+        Config::new()
+            .to_writer(&mut writer, &Params { id: 2 })
+            .unwrap();
+        assert_eq!(writer.buffer(), b"");
+        assert_eq!(writer.get_ref(), b"id=2");
+    }
+
+    #[test]
+    fn test_char_urlencoded() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            currency: char,
+            sep: char,
+            letter: char,
+        }
+        let params = Params {
+            currency: '€',
+            sep: '&',
+            letter: 'a',
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "currency=%E2%82%AC&sep=%26&letter=a"
+        );
+    }
+
+    #[test]
+    fn test_error_path() {
+        #[derive(Debug, Serialize)]
+        struct Inner {
+            a: u32,
+        }
+        #[derive(Debug, Serialize)]
+        struct Options {
+            inner: Inner,
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            id: u32,
+            #[serde(flatten)]
+            options: Options,
+        }
+        let params = Params {
+            id: 1,
+            options: Options {
+                inner: Inner { a: 2 },
+            },
+        };
+        let err = to_string(&params).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported nested struct (at inner)");
+
+        #[derive(Debug, Serialize)]
+        struct Nested {
+            filter: Vec<Vec<Inner>>,
+        }
+        let params = Nested {
+            filter: vec![vec![], vec![Inner { a: 1 }]],
+        };
+        let err = to_string(&params).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported nested struct (at filter[1][0])"
+        );
+        // the message already includes the underlying error, which has no
+        // source
+        assert!(std::error::Error::source(&err).is_none());
+        match err {
+            crate::Error::Path { path, error } => {
+                assert_eq!(path, "filter[1][0]");
+                assert!(matches!(*error, crate::Error::Unsupported(_)));
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_untagged_enum() {
+        #[derive(Debug, Serialize)]
+        #[serde(untagged)]
+        enum Query {
+            Text(&'static str),
+            Id(u32),
+            Range { from: u32, to: u32 },
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: Query,
+        }
+        let params = Params {
+            q: Query::Text("a b"),
+        };
+        assert_eq!(to_string(&params).unwrap(), "q=a+b");
+        let params = Params { q: Query::Id(7) };
+        assert_eq!(to_string(&params).unwrap(), "q=7");
+        // struct variant is a nested struct under a key
+        let params = Params {
+            q: Query::Range { from: 1, to: 2 },
+        };
+        assert!(to_string(&params).is_err());
+        // but is supported at top level
+        assert_eq!(
+            to_string(&Query::Range { from: 1, to: 2 }).unwrap(),
+            "from=1&to=2"
+        );
+    }
+
+    #[test]
+    fn test_empty_struct_field() {
+        #[derive(Debug, Serialize)]
+        struct Marker {}
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: u32,
+            marker: Marker,
+            phantom: std::marker::PhantomData<String>,
+            pair: (Marker, u32),
+            b: u32,
+        }
+        let params = Params {
+            a: 1,
+            marker: Marker {},
+            phantom: std::marker::PhantomData,
+            pair: (Marker {}, 2),
+            b: 3,
+        };
+        assert_eq!(to_string(&params).unwrap(), "a=1&pair=2&b=3");
+    }
+
+    #[test]
+    fn test_extra_params() {
+        #[derive(Debug, Serialize)]
+        struct Empty {}
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: Option<&'static str>,
+        }
+        let config = Config::new().extra_params(&[("api_version", "2"), ("format", "a b")]);
+        assert_eq!(
+            config.to_string(&Empty {}).unwrap(),
+            "api_version=2&format=a+b"
+        );
+        assert_eq!(
+            config.to_string(&Params { q: None }).unwrap(),
+            "api_version=2&format=a+b"
+        );
+        assert_eq!(
+            config.to_string(&Params { q: Some("x") }).unwrap(),
+            "q=x&api_version=2&format=a+b"
+        );
+    }
+
+    #[test]
+    fn test_skip_zero_numbers() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            count: u32,
+            offset: i64,
+            ratio: f64,
+            neg: f32,
+            text: &'static str,
+            ids: Vec<u8>,
+        }
+        let params = Params {
+            count: 0,
+            offset: -1,
+            ratio: 0.0,
+            neg: -0.0,
+            text: "0",
+            ids: vec![0, 1],
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "count=0&offset=-1&ratio=0&neg=-0&text=0&ids=0&ids=1"
+        );
+        let config = Config::new().skip_zero_numbers(true);
+        assert_eq!(config.to_string(&params).unwrap(), "offset=-1&text=0&ids=1");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_to_async_writer() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            page: u32,
+        }
+        let mut writer = tokio::io::BufWriter::new(Vec::new());
+        super::to_async_writer(&mut writer, &Params { q: "a b", page: 2 })
+            .await
+            .unwrap();
+        assert_eq!(writer.get_ref(), b"q=a+b&page=2");
+    }
+
+    #[cfg(feature = "compact_str")]
+    #[test]
+    fn test_to_compact_string() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            page: u32,
+        }
+        let query = super::to_compact_string(&Params { q: "a b", page: 2 }).unwrap();
+        assert_eq!(query, "q=a+b&page=2");
+        assert!(!query.is_heap_allocated());
+    }
+
+    #[cfg(feature = "smol_str")]
+    #[test]
+    fn test_to_smolstr() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            page: u32,
+        }
+        let query = super::to_smolstr(&Params { q: "a b", page: 2 }).unwrap();
+        assert_eq!(query, "q=a+b&page=2");
+        assert!(!query.is_heap_allocated());
+    }
+
+    #[test]
+    fn test_array_format_numbered_suffix() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            filter: Vec<&'static str>,
+            id: u32,
+            pair: (u32, u32),
+        }
+        let params = Params {
+            filter: vec!["a", "b"],
+            id: 7,
+            pair: (3, 4),
+        };
+        let config = Config::new()
+            .array_format(ArrayFormat::NumberedSuffix)
+            .index_base(1);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter1=a&filter2=b&id=7&pair1=3&pair2=4"
+        );
+        let config = Config::new().array_format(ArrayFormat::NumberedSuffix);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter0=a&filter1=b&id=7&pair0=3&pair1=4"
+        );
+    }
+
+    #[test]
+    fn test_max_seq_len() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            ids: Vec<u32>,
+        }
+        let config = Config::new().max_seq_len(3);
+        assert_eq!(
+            config.to_string(&Params { ids: vec![1, 2, 3] }).unwrap(),
+            "ids=1&ids=2&ids=3"
+        );
+        let err = config
+            .to_string(&Params {
+                ids: vec![1, 2, 3, 4],
+            })
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "sequence exceeds the maximum length of 3 (at ids[3])"
+        );
+    }
+
+    #[test]
+    fn test_max_key_len() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            ids: Vec<u32>,
+            #[serde(flatten)]
+            extra: BTreeMap<String, u32>,
+        }
+        let mut params = Params {
+            ids: vec![1, 2],
+            extra: BTreeMap::new(),
+        };
+        params.extra.insert(String::from("abcd"), 3);
+        let config = Config::new().max_key_len(4);
+        assert_eq!(config.to_string(&params).unwrap(), "ids=1&ids=2&abcd=3");
+
+        params.extra.insert(String::from("abcde"), 4);
+        assert_eq!(
+            config.to_string(&params).unwrap_err().to_string(),
+            "key of length 5 exceeds the maximum length of 4 (at abcde)"
+        );
+
+        let config = config.array_format(ArrayFormat::Indexed);
+        assert_eq!(
+            config.to_string(&params).unwrap_err().to_string(),
+            "key of length 6 exceeds the maximum length of 4 (at ids[0])"
+        );
+
+        // the limit applies to the percent-encoded key
+        let mut params = BTreeMap::new();
+        params.insert("$filter", 1);
+        let config = Config::new().max_key_len(7);
+        assert_eq!(
+            config.to_string(&params).unwrap_err().to_string(),
+            "key of length 9 exceeds the maximum length of 7 (at $filter)"
+        );
+        let config = config.encode_keys(false);
+        assert_eq!(config.to_string(&params).unwrap(), "$filter=1");
+    }
+
+    #[test]
+    fn test_unit_format() {
+        #[derive(Debug, Serialize)]
+        struct Marker;
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: u32,
+            debug: (),
+            verbose: Marker,
+            b: Option<()>,
+        }
+        let params = Params {
+            a: 1,
+            debug: (),
+            verbose: Marker,
+            b: None,
+        };
+        assert_eq!(to_string(&params).unwrap(), "a=1");
+        let config = Config::new().unit_format(UnitFormat::Flag);
+        assert_eq!(config.to_string(&params).unwrap(), "a=1&debug&verbose");
+        let config = Config::new().unit_format(UnitFormat::EmptyValue);
+        assert_eq!(config.to_string(&params).unwrap(), "a=1&debug=&verbose=");
+        // top level unit is still empty
+        assert_eq!(config.to_string(&()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_strict() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            filter: Vec<&'static str>,
+            #[serde(rename = "filter2")]
+            other: Option<&'static str>,
+            matrix: Vec<Vec<u32>>,
+        }
+        let config = Config::new().strict(true);
+        let params = Params {
+            filter: vec!["a", "b"],
+            other: Some("c"),
+            matrix: vec![],
+        };
+        let err = config.to_string(&params).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported empty sequence in strict mode (at matrix)"
+        );
+        assert_eq!(to_string(&params).unwrap(), "filter=a&filter=b&filter2=c");
+
+        let params = Params {
+            filter: vec!["a"],
+            other: None,
+            matrix: vec![vec![1]],
+        };
+        let err = config.to_string(&params).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported nested sequence in strict mode (at matrix[0])"
+        );
+
+        #[derive(Debug, Serialize)]
+        struct Duplicate {
+            a: u32,
+            #[serde(rename = "a")]
+            b: u32,
+        }
+        let err = config.to_string(&Duplicate { a: 1, b: 2 }).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate key `a` in strict mode (at a)");
+        assert_eq!(to_string(&Duplicate { a: 1, b: 2 }).unwrap(), "a=1&a=2");
+    }
+    #[test]
+    fn test_sets() {
+        use std::collections::{BTreeSet, HashMap, HashSet};
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            tag: HashSet<&'static str>,
+            id: BTreeSet<u32>,
+            opt: Vec<Option<&'static str>>,
+        }
+        let params = Params {
+            tag: vec!["x y", "b", "a", "c"].into_iter().collect(),
+            id: vec![3, 1, 2].into_iter().collect(),
+            opt: vec![Some("z"), None, Some("y")],
+        };
+        let config = Config::new().sort_seq_values(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "tag=a&tag=b&tag=c&tag=x+y&id=1&id=2&id=3&opt=y&opt=z"
+        );
+        // without sorting, the natural order of `BTreeSet` is preserved
+        let query = to_string(&params).unwrap();
+        assert!(query.ends_with("&id=1&id=2&id=3&opt=z&opt=y"));
+
+        let ids: BTreeSet<u32> = vec![10, 9].into_iter().collect();
+        let mut params = HashMap::new();
+        params.insert("id", ids);
+        assert_eq!(config.to_string(&params).unwrap(), "id=10&id=9");
+    }
+
+    #[test]
+    fn test_report() {
+        use crate::Warning;
+
+        #[derive(Debug, Serialize)]
+        struct Options {
+            actors: Vec<Option<&'static str>>,
+            unit: (),
+        }
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            page: Option<u32>,
+            filter: Vec<u32>,
+            active: bool,
+            offset: u32,
+            #[serde(flatten)]
+            options: Options,
+        }
+        let params = Params {
+            q: "x",
+            page: None,
+            filter: vec![],
+            active: false,
+            offset: 0,
+            options: Options {
+                actors: vec![Some("a"), None],
+                unit: (),
+            },
+        };
+        let (query, warnings) = crate::to_string_with_report(&params).unwrap();
+        assert_eq!(query, "q=x&active=false&offset=0&actors=a");
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::DroppedNone(String::from("page")),
+                Warning::DroppedEmptySequence(String::from("filter")),
+                Warning::DroppedNone(String::from("actors[1]")),
+                Warning::DroppedUnit(String::from("unit")),
+            ]
+        );
+
+        let config = Config::new()
+            .bool_format(BoolFormat::FlagIfTrue)
+            .skip_zero_numbers(true);
+        let (query, warnings) = config.to_string_with_report(&params).unwrap();
+        assert_eq!(query, "q=x&actors=a");
+        assert_eq!(warnings[2], Warning::DroppedFalse(String::from("active")));
+        assert_eq!(warnings[3].to_string(), "dropped zero number `offset`");
+    }
+
+    #[test]
+    fn test_key_collision() {
+        use crate::{KeyCollision, Warning};
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            id: u32,
+            tag: Vec<&'static str>,
+            #[serde(flatten)]
+            extra: BTreeMap<&'static str, &'static str>,
+        }
+        let mut extra = BTreeMap::new();
+        extra.insert("id", "x");
+        extra.insert("other", "y");
+        extra.insert("tag", "z");
+        let params = Params {
+            id: 1,
+            tag: vec!["a", "b"],
+            extra,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "id=1&tag=a&tag=b&id=x&other=y&tag=z"
+        );
+
+        let config = Config::new().key_collision(KeyCollision::FirstWins);
+        let (query, warnings) = config.to_string_with_report(&params).unwrap();
+        assert_eq!(query, "id=1&tag=a&tag=b&other=y");
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::DroppedDuplicateKey(String::from("id")),
+                Warning::DroppedDuplicateKey(String::from("tag")),
+            ]
+        );
+
+        let config = Config::new().key_collision(KeyCollision::LastWins);
+        assert_eq!(config.to_string(&params).unwrap(), "id=x&other=y&tag=z");
+
+        let config = Config::new().key_collision(KeyCollision::Error);
+        let err = config.to_string(&params).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate key `id` (at id)");
+    }
+
+    #[test]
+    fn test_array_format_indexed() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            filter: Vec<&'static str>,
+            empty: Vec<u32>,
+            pair: (u32, Option<u32>, u32),
+        }
+        let params = Params {
+            filter: vec!["a", "b"],
+            empty: vec![],
+            pair: (3, None, 4),
+        };
+        let config = Config::new().array_format(ArrayFormat::Indexed);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter[0]=a&filter[1]=b&pair[0]=3&pair[2]=4"
+        );
+        let config = config.index_base(1);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter[1]=a&filter[2]=b&pair[1]=3&pair[3]=4"
+        );
+    }
+
+    #[test]
+    fn test_nonzero_integers() {
+        use std::collections::BTreeMap;
+        use std::num::{NonZeroI8, NonZeroU128, NonZeroU32, NonZeroU64};
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            id: NonZeroU64,
+            parent: Option<NonZeroU32>,
+            offsets: Vec<NonZeroI8>,
+            big: NonZeroU128,
+            #[serde(flatten)]
+            names: BTreeMap<NonZeroU64, &'static str>,
+        }
+        let mut names = BTreeMap::new();
+        names.insert(NonZeroU64::new(2).unwrap(), "b");
+        names.insert(NonZeroU64::new(1).unwrap(), "a");
+        let params = Params {
+            id: NonZeroU64::new(u64::MAX).unwrap(),
+            parent: NonZeroU32::new(0),
+            offsets: vec![NonZeroI8::new(-1).unwrap(), NonZeroI8::new(5).unwrap()],
+            big: NonZeroU128::new(u128::MAX).unwrap(),
+            names,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            format!(
+                "id=18446744073709551615&offsets=-1&offsets=5&big={}&1=a&2=b",
+                u128::MAX
+            )
+        );
+    }
+
+    #[test]
+    fn test_array_format_brackets() {
+        use crate::BracketContent;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            filter: Vec<&'static str>,
+            id: u32,
+            pair: (u32, u32),
+        }
+        let params = Params {
+            filter: vec!["a", "b"],
+            id: 7,
+            pair: (3, 4),
+        };
+        let config = Config::new().array_format(ArrayFormat::Brackets);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter[]=a&filter[]=b&id=7&pair[]=3&pair[]=4"
+        );
+        let config = config.bracket_content(BracketContent::Index);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter[0]=a&filter[1]=b&id=7&pair[0]=3&pair[1]=4"
+        );
+        let config = config.index_base(1);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter[1]=a&filter[2]=b&id=7&pair[1]=3&pair[2]=4"
+        );
+        let config = config.bracket_content(BracketContent::Custom(String::from("in")));
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter[in]=a&filter[in]=b&id=7&pair[in]=3&pair[in]=4"
+        );
+    }
+
+    #[test]
+    fn test_trailing_separator() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: Option<u32>,
+            b: Vec<u32>,
+        }
+        #[derive(Debug, Serialize)]
+        struct Empty {}
+
+        let config = Config::new().trailing_separator(true);
+        let params = Params {
+            a: Some(1),
+            b: vec![2, 3],
+        };
+        assert_eq!(config.to_string(&params).unwrap(), "a=1&b=2&b=3&");
+        let params = Params { a: None, b: vec![] };
+        assert_eq!(config.to_string(&params).unwrap(), "");
+        assert_eq!(config.to_string(&Empty {}).unwrap(), "");
+        let config = config.extra_params(&[("v", "2")]);
+        assert_eq!(config.to_string(&Empty {}).unwrap(), "v=2&");
+    }
+
+    #[test]
+    fn test_array_format_comma() {
+        #[derive(Debug, Serialize)]
+        struct Point(u32, u32);
+        #[derive(Debug, Serialize)]
+        struct Params {
+            field: (u32, &'static str, f64),
+            point: Point,
+            tags: Vec<&'static str>,
+            empty: Vec<u32>,
+            opt: Vec<Option<u32>>,
+            id: u32,
+        }
+        let params = Params {
+            field: (1, "hello", 2.5),
+            point: Point(4, 5),
+            tags: vec!["a,b", "c d"],
+            empty: vec![],
+            opt: vec![None, Some(2), None],
+            id: 7,
+        };
+        let config = Config::new().array_format(ArrayFormat::Comma);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "field=1,hello,2.5&point=4,5&tags=a%2Cb,c+d&opt=2&id=7"
+        );
+        let config = config.sort_seq_values(true);
+        let params = Params {
+            field: (1, "hello", 2.5),
+            point: Point(5, 4),
+            tags: vec!["z", "y"],
+            empty: vec![],
+            opt: vec![],
+            id: 7,
+        };
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "field=1,2.5,hello&point=4,5&tags=y,z&id=7"
+        );
+
+        let config = Config::new()
+            .array_format(ArrayFormat::Comma)
+            .newtype_variant_format(NewtypeVariantFormat::TaggedColon);
+        let value: Result<Vec<u32>, ()> = Ok(vec![1, 2]);
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("r", value);
+        assert_eq!(config.to_string(&map).unwrap(), "r=Ok:1,2");
+    }
+
+    #[test]
+    fn test_sort_map_keys() {
+        use std::collections::{HashMap, HashSet};
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            z: u32,
+            #[serde(flatten)]
+            extra: HashMap<String, Vec<u32>>,
+        }
+        let mut extra = HashMap::new();
+        for (i, key) in ["d", "b", "y", "a", "c"].iter().enumerate() {
+            extra.insert(key.to_string(), vec![i as u32, 9]);
+        }
+        extra.insert(String::from("e"), vec![]);
+        let params = Params { z: 0, extra };
+        let config = Config::new().sort_map_keys(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "a=3&a=9&b=1&b=9&c=4&c=9&d=0&d=9&y=2&y=9&z=0"
+        );
+
+        let set: HashSet<u32> = vec![30, 10, 20].into_iter().collect();
+        let mut params = HashMap::new();
+        params.insert("q", set);
+        params.insert("a", HashSet::new());
+        let config = config.sort_seq_values(true).extra_params(&[("v", "1")]);
+        assert_eq!(config.to_string(&params).unwrap(), "q=10&q=20&q=30&v=1");
+    }
+
+    #[test]
+    fn test_serializer_new_with_config() {
+        use crate::Serializer;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: bool,
+            b: Vec<u32>,
+        }
+        let config = Config::new()
+            .bool_format(BoolFormat::FlagIfTrue)
+            .trailing_separator(true);
+        let mut ser = Serializer::new_with_config(Vec::new(), config);
+        let params = Params {
+            a: true,
+            b: vec![1, 2],
+        };
+        params.serialize(&mut ser).unwrap();
+        ser.finish().unwrap();
+        assert_eq!(ser.into_inner(), b"a&b=1&b=2&");
+
+        let mut ser = Serializer::new(Vec::new());
+        params.serialize(&mut ser).unwrap();
+        ser.finish().unwrap();
+        assert_eq!(ser.into_inner(), b"a=true&b=1&b=2");
+
+        // LastWins applies to prepared values
+        #[derive(Debug, Serialize)]
+        struct Collide {
+            id: u32,
+            #[serde(flatten)]
+            extra: std::collections::BTreeMap<&'static str, u32>,
+        }
+        let first = Collide {
+            id: 1,
+            extra: vec![("id", 2)].into_iter().collect(),
+        };
+        let second = Collide {
+            id: 3,
+            extra: vec![("v", 4)].into_iter().collect(),
+        };
+        let config = Config::new()
+            .key_collision(crate::KeyCollision::LastWins)
+            .extra_params(&[("v", "1")]);
+        let mut ser = Serializer::new_with_config(Vec::new(), config.clone());
+        for value in &[&first, &second] {
+            ser.prepare(value).unwrap();
+            value.serialize(&mut ser).unwrap();
+        }
+        ser.finish().unwrap();
+        assert_eq!(ser.into_inner(), b"id=2&id=3&v=1");
+        let mut ser = Serializer::new_with_config(Vec::new(), config);
+        first.serialize(&mut ser).unwrap();
+        second.serialize(&mut ser).unwrap();
+        ser.finish().unwrap();
+        assert_eq!(ser.into_inner(), b"id=1&id=2&id=3&v=4&v=1");
+    }
+
+    #[test]
+    fn test_bool_formats() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: bool,
+            b: bool,
+            c: Vec<bool>,
+        }
+        let params = Params {
+            a: true,
+            b: false,
+            c: vec![false, true],
+        };
+        let cases = [
+            (BoolFormat::TrueFalse, "a=true&b=false&c=false&c=true"),
+            (BoolFormat::Numeric, "a=1&b=0&c=0&c=1"),
+            (BoolFormat::YesNo, "a=yes&b=no&c=no&c=yes"),
+            (BoolFormat::OnOff, "a=on&b=off&c=off&c=on"),
+            (BoolFormat::FlagIfTrue, "a&c"),
+            (BoolFormat::OmitIfFalse, "a=true&c=true"),
+        ];
+        for (format, expected) in cases.iter() {
+            let config = Config::new().bool_format(*format);
+            assert_eq!(config.to_string(&params).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_on_pair() {
+        use crate::KeyCollision;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            tag: Vec<&'static str>,
+            active: bool,
+            unit: (),
+            none: Option<u32>,
+        }
+        let params = Params {
+            q: "a b",
+            tag: vec!["x", "x", "y"],
+            active: true,
+            unit: (),
+            none: None,
+        };
+        let pairs = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&pairs);
+        let config = Config::new()
+            .unit_format(UnitFormat::Flag)
+            .key_collision(KeyCollision::LastWins)
+            .on_pair(move |key, value| {
+                sink.lock()
+                    .unwrap()
+                    .push((key.to_string(), value.to_string()))
+            });
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            to_string(&params).unwrap() + "&unit"
+        );
+        let pairs = pairs.lock().unwrap();
+        let pairs: Vec<_> = pairs
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("q", "a b"),
+                ("tag", "x"),
+                ("tag", "x"),
+                ("tag", "y"),
+                ("active", "true"),
+                ("unit", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optional_bool() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: Option<bool>,
+            b: Option<bool>,
+            c: Option<bool>,
+        }
+        let params = Params {
+            a: Some(true),
+            b: Some(false),
+            c: None,
+        };
+        let cases = [
+            (BoolFormat::TrueFalse, "a=true&b=false"),
+            (BoolFormat::Numeric, "a=1&b=0"),
+            (BoolFormat::YesNo, "a=yes&b=no"),
+            (BoolFormat::OnOff, "a=on&b=off"),
+            // `Some(false)` is indistinguishable from `None`
+            (BoolFormat::FlagIfTrue, "a"),
+            (BoolFormat::OmitIfFalse, "a=true"),
+        ];
+        for (format, expected) in cases.iter() {
+            let config = Config::new().bool_format(*format);
+            assert_eq!(config.to_string(&params).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_path() {
+        use std::path::{Path, PathBuf};
+
+        #[derive(Debug, Serialize)]
+        struct Params<'a> {
+            file: PathBuf,
+            dir: &'a Path,
+            extra: Vec<PathBuf>,
+        }
+        let params = Params {
+            file: PathBuf::from("/tmp/a b.txt"),
+            dir: Path::new("rel/ä"),
+            extra: vec![PathBuf::from("x&y")],
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "file=%2Ftmp%2Fa+b.txt&dir=rel%2F%C3%A4&extra=x%26y"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+
+            let params = Params {
+                file: PathBuf::from("ok"),
+                dir: Path::new(OsStr::from_bytes(b"invalid\xff")),
+                extra: vec![],
+            };
+            assert_eq!(
+                to_string(&params).unwrap_err().to_string(),
+                "path contains invalid UTF-8 characters (at dir)"
+            );
+        }
+    }
+
+    #[test]
+    fn test_to_form_urlencoded() {
+        use crate::helpers::Raw;
+        use crate::to_form_urlencoded;
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            n: Vec<f64>,
+            flag: bool,
+            none: Option<u32>,
+            #[serde(flatten)]
+            extra: BTreeMap<&'static str, char>,
+        }
+        let mut extra = BTreeMap::new();
+        extra.insert("k=y", '€');
+        let params = Params {
+            q: "a b/c~",
+            n: vec![1.5, -2.0],
+            flag: true,
+            none: None,
+            extra,
+        };
+        let expected = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("q", "a b/c~")
+            .append_pair("n", "1.5")
+            .append_pair("n", "-2")
+            .append_pair("flag", "true")
+            .append_pair("k=y", "€")
+            .finish();
+        assert_eq!(to_form_urlencoded(&params).unwrap(), expected);
+        assert_eq!(
+            expected,
+            "q=a+b%2Fc%7E&n=1.5&n=-2&flag=true&k%3Dy=%E2%82%AC"
+        );
+
+        // flags are written with an empty value
+        let config = Config::new().bool_format(BoolFormat::FlagIfTrue);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=a+b%2Fc%7E&n=1.5&n=-2&flag&k%3Dy=%E2%82%AC"
+        );
+        assert_eq!(
+            config.to_form_urlencoded(&params).unwrap(),
+            "q=a+b%2Fc%7E&n=1.5&n=-2&flag=&k%3Dy=%E2%82%AC"
+        );
+
+        #[derive(Debug, Serialize)]
+        struct WithRaw {
+            raw: Raw,
+        }
+        let params = WithRaw {
+            raw: Raw(String::from("a=1")),
+        };
+        assert!(to_form_urlencoded(&params).is_err());
+    }
+
+    #[test]
+    fn test_top_level_scalar() {
+        use crate::TopLevelPolicy;
+
+        let config = Config::new().top_level_scalar(TopLevelPolicy::Empty);
+        assert_eq!(config.to_string(&42).unwrap(), "");
+        assert_eq!(config.to_string("text").unwrap(), "");
+        assert_eq!(config.to_string(&vec![1, 2]).unwrap(), "");
+        #[derive(Debug, Serialize)]
+        struct Params {
+            a: u32,
+        }
+        assert_eq!(config.to_string(&Params { a: 1 }).unwrap(), "a=1");
+
+        let config = Config::new().top_level_scalar(TopLevelPolicy::Error);
+        assert_eq!(
+            config.to_string(&42).unwrap_err().to_string(),
+            "cannot serialize top level value"
+        );
+    }
+
+    #[test]
+    fn test_unit_variant_format() {
+        use crate::{ArrayFormat, UnitVariantFormat};
+
+        #[derive(Debug, Serialize)]
+        enum Selection {
+            A,
+            B,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct Form {
+            select: Selection,
+            extra: Vec<Selection>,
+            page: u32,
+        }
+        let form = Form {
+            select: Selection::A,
+            extra: vec![Selection::A, Selection::B],
+            page: 2,
+        };
+
+        assert_eq!(to_string(&form).unwrap(), "select=A&extra=A&extra=B&page=2");
+        let config = Config::new().unit_variant_format(UnitVariantFormat::Name);
+        assert_eq!(
+            config.to_string(&form).unwrap(),
+            "select=A&extra=A&extra=B&page=2"
+        );
+
+        let config = Config::new().unit_variant_format(UnitVariantFormat::KeyWithOne);
+        assert_eq!(config.to_string(&form).unwrap(), "A=1&A=1&B=1&page=2");
+        let config = config.array_format(ArrayFormat::Comma);
+        assert_eq!(config.to_string(&form).unwrap(), "A=1&A=1&B=1&page=2");
+    }
+
+    #[test]
+    fn test_trim_values() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            name: &'static str,
+            tags: Vec<String>,
+            sep: char,
+        }
+        let params = Params {
+            name: "  hello  ",
+            tags: vec![String::from("\ta b\r\n"), String::from(" ")],
+            sep: ' ',
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "name=++hello++&tags=%09a+b%0D%0A&tags=+&sep=+"
+        );
+        let config = Config::new().trim_values(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "name=hello&tags=a+b&tags=&sep="
+        );
+    }
+
+    #[test]
+    fn test_strict_units() {
+        use crate::{Error, UnitFormat};
+
+        #[derive(Debug, Serialize)]
+        struct Marker;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            id: u32,
+            marker: Marker,
+        }
+        let params = Params {
+            id: 1,
+            marker: Marker,
+        };
+        assert_eq!(to_string(&params).unwrap(), "id=1");
+
+        let config = Config::new().strict_units(true);
+        match config.to_string(&params).unwrap_err() {
+            Error::Path { path, error } => {
+                assert_eq!(path, "marker");
+                assert!(matches!(*error, Error::Unsupported(ref msg) if msg == "unit"));
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+        let config = config.unit_format(UnitFormat::Flag);
+        match config.to_string(&params).unwrap_err() {
+            Error::Path { error, .. } => {
+                assert!(matches!(*error, Error::Unsupported(ref msg) if msg == "unit"));
+            }
+            err => panic!("unexpected error: {:?}", err),
+        }
+        assert_eq!(config.to_string(&()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_array_format_indexed_nested() {
+        #[derive(Debug, Serialize)]
+        struct Params {
+            m: Vec<Vec<u32>>,
+            cube: Vec<Vec<Vec<u32>>>,
+        }
+        let params = Params {
+            m: vec![vec![1, 2], vec![], vec![3]],
+            cube: vec![vec![vec![4], vec![5, 6]]],
+        };
+        let config = Config::new().array_format(ArrayFormat::Indexed);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "m[0][0]=1&m[0][1]=2&m[2][0]=3&cube[0][0][0]=4&cube[0][1][0]=5&cube[0][1][1]=6"
+        );
+        let config = config.index_base(1);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "m[1][1]=1&m[1][2]=2&m[3][1]=3&cube[1][1][1]=4&cube[1][2][1]=5&cube[1][2][2]=6"
+        );
+    }
+
+    #[test]
+    fn test_bytes_format() {
+        use crate::BytesFormat;
+        use std::borrow::Cow;
+
+        #[derive(Clone, Debug, Serialize)]
+        struct Params<'a> {
+            #[serde(serialize_with = "crate::helpers::bytes")]
+            owned: Vec<u8>,
+            #[serde(serialize_with = "crate::helpers::bytes")]
+            borrowed: &'a [u8],
+            #[serde(serialize_with = "crate::helpers::bytes")]
+            cow: Cow<'a, [u8]>,
+            #[serde(serialize_with = "crate::helpers::bytes")]
+            empty: Vec<u8>,
+            plain: &'a [u8],
+        }
+        let params = Params {
+            owned: vec![0xfb, 0xff],
+            borrowed: b"any carnal pleas",
+            cow: Cow::Borrowed(b"f"),
+            empty: vec![],
+            plain: &[1, 2],
+        };
+
+        assert_eq!(
+            to_string(&Params {
+                borrowed: b"ab",
+                ..params.clone()
+            })
+            .unwrap(),
+            "owned=251&owned=255&borrowed=97&borrowed=98&cow=102&plain=1&plain=2"
+        );
+
+        let config = Config::new().bytes_format(BytesFormat::Base64);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "owned=%2B%2F8%3D&borrowed=YW55IGNhcm5hbCBwbGVhcw%3D%3D&cow=Zg%3D%3D&empty=&plain=1&plain=2"
+        );
+        let params = Params {
+            owned: b"foob".to_vec(),
+            borrowed: b"fooba",
+            cow: Cow::Owned(b"foobar".to_vec()),
+            ..params
+        };
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "owned=Zm9vYg%3D%3D&borrowed=Zm9vYmE%3D&cow=Zm9vYmFy&empty=&plain=1&plain=2"
+        );
+
+        let config = Config::new().bytes_format(BytesFormat::Hex);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "owned=666f6f62&borrowed=666f6f6261&cow=666f6f626172&empty=&plain=1&plain=2"
+        );
+    }
+
+    #[test]
+    fn test_coalesce_duplicate_keys() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            #[serde(rename = "filter")]
+            status: &'static str,
+            tags: Vec<&'static str>,
+            #[serde(rename = "filter")]
+            owner: Vec<&'static str>,
+            #[serde(flatten)]
+            extra: BTreeMap<&'static str, &'static str>,
+        }
+        let mut extra = BTreeMap::new();
+        extra.insert("page", "2");
+        extra.insert("tags", "x&y");
+        let params = Params {
+            status: "open",
+            tags: vec!["a"],
+            owner: vec!["me", "you"],
+            extra,
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "filter=open&tags=a&filter=me&filter=you&page=2&tags=x%26y"
+        );
+        let config = Config::new().coalesce_duplicate_keys(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter=open,me,you&tags=a,x%26y&page=2"
+        );
+
+        // Repeated keys of a single sequence are kept.
+        let params = Params {
+            status: "open",
+            tags: vec!["a", "b"],
+            owner: vec![],
+            extra: BTreeMap::new(),
+        };
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter=open&tags=a&tags=b"
+        );
+
+        // Raw fragments keep their position.
+        #[derive(Debug, Serialize)]
+        struct WithRaw {
+            #[serde(rename = "f")]
+            a: Vec<u32>,
+            #[serde(rename = "f")]
+            b: u32,
+            raw: crate::helpers::Raw,
+            z: u32,
+        }
+        let params = WithRaw {
+            a: vec![1, 2, 3],
+            b: 4,
+            raw: crate::helpers::Raw(String::from("r=1&q")),
+            z: 9,
+        };
+        assert_eq!(config.to_string(&params).unwrap(), "f=1,2,3,4&r=1&q&z=9");
+    }
+
+    #[test]
+    fn test_encode_set_unreserved() {
+        use crate::EncodeSet;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            value: &'static str,
+        }
+        let config = Config::new().encode_set(EncodeSet::Unreserved);
+        // Test vectors of RFC 5849 (OAuth 1.0), section 3.4.1.3.1, and of
+        // the percent-encoding of AWS Signature Version 4.
+        let cases = [
+            ("Ladies + Gentlemen", "Ladies%20%2B%20Gentlemen"),
+            ("An encoded string!", "An%20encoded%20string%21"),
+            ("Dogs, Cats & Mice", "Dogs%2C%20Cats%20%26%20Mice"),
+            ("\u{2603}", "%E2%98%83"),
+            ("c2&a3=2+q", "c2%26a3%3D2%2Bq"),
+            ("AZaz09-._~", "AZaz09-._~"),
+            (
+                "!*'();:@$,/?#[]",
+                "%21%2A%27%28%29%3B%3A%40%24%2C%2F%3F%23%5B%5D",
+            ),
+            ("%7E", "%257E"),
+        ];
+        for (value, expected) in cases.iter() {
+            assert_eq!(
+                config.to_string(&Params { value }).unwrap(),
+                format!("value={}", expected)
+            );
+        }
+        assert_eq!(
+            to_string(&Params { value: "a b~*" }).unwrap(),
+            "value=a+b%7E*"
+        );
+    }
+
+    #[test]
+    fn test_order() {
+        use crate::Order;
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            b: u32,
+            tags: Vec<&'static str>,
+            #[serde(flatten)]
+            extra: BTreeMap<&'static str, u32>,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct Simple {
+            b: u32,
+            tags: Vec<&'static str>,
+            a: Option<u32>,
+        }
+
+        let mut extra = BTreeMap::new();
+        extra.insert("c", 3);
+        extra.insert("a", 1);
+        let params = Params {
+            b: 2,
+            tags: vec!["y", "x"],
+            extra,
+        };
+        let simple = Simple {
+            b: 2,
+            tags: vec!["y", "x"],
+            a: Some(1),
+        };
+
+        let config = Config::new().order(Order::Declaration);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "b=2&tags=y&tags=x&a=1&c=3"
+        );
+        assert_eq!(config.to_string(&simple).unwrap(), "b=2&tags=y&tags=x&a=1");
+
+        let config = Config::new().order(Order::Reverse);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "c=3&a=1&tags=y&tags=x&b=2"
+        );
+        assert_eq!(config.to_string(&simple).unwrap(), "a=1&tags=y&tags=x&b=2");
+
+        let config = Config::new().order(Order::Sorted);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "a=1&b=2&c=3&tags=y&tags=x"
+        );
+        assert_eq!(config.to_string(&simple).unwrap(), "a=1&b=2&tags=y&tags=x");
+
+        // sort_map_keys does not sort struct fields
+        let config = Config::new().sort_map_keys(true);
+        assert_eq!(config.to_string(&simple).unwrap(), "b=2&tags=y&tags=x&a=1");
+
+        // coalesced keys are ordered by their first occurrence
+        #[derive(Debug, Serialize)]
+        struct Coalesced {
+            z: u32,
+            #[serde(rename = "f")]
+            a: Vec<u32>,
+            raw: crate::helpers::Raw,
+            #[serde(rename = "f")]
+            b: u32,
+        }
+        let params = Coalesced {
+            z: 9,
+            a: vec![1, 2, 3],
+            raw: crate::helpers::Raw(String::from("r=1&q")),
+            b: 4,
+        };
+        let config = Config::new().coalesce_duplicate_keys(true);
+        assert_eq!(config.to_string(&params).unwrap(), "z=9&f=1,2,3,4&r=1&q");
+        let config = config.order(Order::Reverse);
+        assert_eq!(config.to_string(&params).unwrap(), "r=1&q&f=1,2,3,4&z=9");
+        let config = config.order(Order::Sorted);
+        assert_eq!(config.to_string(&params).unwrap(), "f=1,2,3,4&r=1&q&z=9");
+        let mut map = std::collections::HashMap::new();
+        map.insert("b", 2);
+        map.insert("a", 1);
+        map.insert("c", 3);
+        let config = Config::new()
+            .coalesce_duplicate_keys(true)
+            .sort_map_keys(true);
+        assert_eq!(config.to_string(&map).unwrap(), "a=1&b=2&c=3");
+    }
+
+    #[test]
+    fn test_flattened_child_params() {
+        use crate::helpers::Flattened;
+        use crate::QueryParams;
+
+        #[derive(Debug, Serialize)]
+        struct Parent {
+            id: u32,
+            #[serde(flatten)]
+            query: QueryParams,
+            #[serde(flatten)]
+            pairs: Flattened<Vec<(&'static str, u32)>>,
+            page: u32,
+        }
+        let parent = Parent {
+            id: 7,
+            query: "q=a+b&tag=x&tag=y".parse().unwrap(),
+            pairs: Flattened(vec![("limit", 10), ("offset", 20)]),
+            page: 2,
+        };
+        assert_eq!(
+            to_string(&parent).unwrap(),
+            "id=7&q=a+b&tag=x&tag=y&limit=10&offset=20&page=2"
+        );
+
+        let parent = Parent {
+            id: 7,
+            query: QueryParams::new(),
+            pairs: Flattened(vec![]),
+            page: 2,
+        };
+        assert_eq!(to_string(&parent).unwrap(), "id=7&page=2");
+
+        let query: QueryParams = "a=1&b=x%26y&a=2".parse().unwrap();
+        assert_eq!(to_string(&query).unwrap(), "a=1&b=x%26y&a=2");
+    }
+
+    #[test]
+    fn test_newline_normalization() {
+        use crate::NewlinePolicy;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            lf: &'static str,
+            crlf: &'static str,
+            cr: &'static str,
+            mixed: &'static str,
+        }
+        let params = Params {
+            lf: "a\nb",
+            crlf: "a\r\nb",
+            cr: "a\rb",
+            mixed: "\r\n\n\r",
+        };
+        let expected = [
+            (
+                NewlinePolicy::AsIs,
+                "lf=a%0Ab&crlf=a%0D%0Ab&cr=a%0Db&mixed=%0D%0A%0A%0D",
+            ),
+            (
+                NewlinePolicy::Lf,
+                "lf=a%0Ab&crlf=a%0Ab&cr=a%0Ab&mixed=%0A%0A%0A",
+            ),
+            (
+                NewlinePolicy::CrLf,
+                "lf=a%0D%0Ab&crlf=a%0D%0Ab&cr=a%0D%0Ab&mixed=%0D%0A%0D%0A%0D%0A",
+            ),
+        ];
+        for (policy, expected) in expected.iter() {
+            let config = Config::new().newline_normalization(*policy);
+            assert_eq!(config.to_string(&params).unwrap(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_explained() {
+        use crate::{BoolFormat, FieldOutcome};
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            page: Option<u32>,
+            tags: Vec<&'static str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cursor: Option<u32>,
+            active: bool,
+            #[serde(skip)]
+            _internal: u32,
+            #[serde(flatten)]
+            extra: BTreeMap<&'static str, Option<u32>>,
+        }
+        let mut extra = BTreeMap::new();
+        extra.insert("x", Some(1));
+        extra.insert("y", None);
+        let params = Params {
+            q: "rust",
+            page: None,
+            tags: vec![],
+            cursor: None,
+            active: false,
+            _internal: 0,
+            extra,
+        };
+        let config = Config::new().bool_format(BoolFormat::FlagIfTrue);
+        let (query, explanations) = config.to_string_explained(&params).unwrap();
+        assert_eq!(query, "q=rust&x=1");
+        let explanations: Vec<_> = explanations
+            .into_iter()
+            .map(|(key, outcome)| format!("{}: {}", key, outcome))
+            .collect();
+        assert_eq!(
+            explanations,
+            [
+                "q: emitted",
+                "page: dropped none value `page`",
+                "tags: dropped empty sequence `tags`",
+                "active: dropped false flag `active`",
+                "x: emitted",
+                "y: dropped none value `y`",
+            ]
+        );
+        // Without a flattened field, the struct is not serialized as a map,
+        // so skipped fields are reported as well.
+        #[derive(Debug, Serialize)]
+        struct Simple {
+            q: &'static str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            cursor: Option<u32>,
+        }
+        let (query, explanations) = crate::to_string_explained(&Simple {
+            q: "rust",
+            cursor: None,
+        })
+        .unwrap();
+        assert_eq!(query, "q=rust");
+        assert_eq!(
+            explanations,
+            [
+                (String::from("q"), FieldOutcome::Emitted),
+                (String::from("cursor"), FieldOutcome::SkippedByPredicate),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_extra_and_skip() {
+        use crate::EncodeSet;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+        }
+        let params = Params { q: "a~b c*!'()é" };
+        assert_eq!(to_string(&params).unwrap(), "q=a%7Eb+c*%21%27%28%29%C3%A9");
+
+        let config = Config::new().encode_set(EncodeSet::Unreserved);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=a~b%20c%2A%21%27%28%29%C3%A9"
+        );
+        let config = config.encode_extra(b"~");
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=a%7Eb%20c%2A%21%27%28%29%C3%A9"
+        );
+        let config = config.encode_skip(b"!*'()");
+        assert_eq!(config.to_string(&params).unwrap(), "q=a%7Eb%20c*!'()%C3%A9");
+
+        // The later call wins, and non-ASCII bytes are always encoded.
+        let config = Config::new()
+            .encode_extra(b" ~")
+            .encode_skip("~é".as_bytes());
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=a~b%20c*%21%27%28%29%C3%A9"
+        );
+    }
+
+    #[test]
+    fn test_pairs() {
+        use crate::helpers::PreEncoded;
+        use crate::{to_encoded_pairs, to_pairs, EncodeSet, NewtypeVariantFormat};
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: &'static str,
+            ids: Vec<u32>,
+            r: Result<&'static str, ()>,
+            none: Option<u32>,
+        }
+        let params = Params {
+            q: "a b~",
+            ids: vec![1, 2],
+            r: Ok("x/y"),
+            none: None,
+        };
+        let pairs = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|&(k, v)| (String::from(k), String::from(v)))
+                .collect()
+        };
+        assert_eq!(
+            to_pairs(&params).unwrap(),
+            pairs(&[("q", "a b~"), ("ids", "1"), ("ids", "2"), ("r", "x/y")])
+        );
+        assert_eq!(
+            to_encoded_pairs(&params).unwrap(),
+            pairs(&[("q", "a+b%7E"), ("ids", "1"), ("ids", "2"), ("r", "x%2Fy")])
+        );
+
+        let config = Config::new()
+            .encode_set(EncodeSet::Unreserved)
+            .newtype_variant_format(NewtypeVariantFormat::TaggedColon);
+        assert_eq!(
+            config.to_pairs(&params).unwrap(),
+            pairs(&[("q", "a b~"), ("ids", "1"), ("ids", "2"), ("r", "x/y")])
+        );
+        let encoded = config.to_encoded_pairs(&params).unwrap();
+        assert_eq!(
+            encoded,
+            pairs(&[
+                ("q", "a%20b~"),
+                ("ids", "1"),
+                ("ids", "2"),
+                ("r", "Ok:x%2Fy")
+            ])
+        );
+        let joined: Vec<_> = encoded
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect();
+        assert_eq!(joined.join("&"), config.to_string(&params).unwrap());
+
+        #[derive(Debug, Serialize)]
+        struct Signed {
+            sig: PreEncoded,
+        }
+        let signed = Signed {
+            sig: PreEncoded(String::from("a%2Fb")),
+        };
+        assert!(to_pairs(&signed).is_err());
+        assert_eq!(
+            to_encoded_pairs(&signed).unwrap(),
+            pairs(&[("sig", "a%2Fb")])
+        );
+    }
+
+    #[test]
+    fn test_key_case() {
+        use crate::KeyCase;
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize)]
+        enum Search {
+            ByUser {
+                user_name: &'static str,
+                page_size: u32,
+            },
+        }
+        #[derive(Serialize)]
+        struct Params {
+            sort_order: &'static str,
+            #[serde(rename = "max_hit_count")]
+            limit: u32,
+        }
+
+        let search = Search::ByUser {
+            user_name: "boxdot",
+            page_size: 20,
+        };
+        let params = Params {
+            sort_order: "asc",
+            limit: 5,
+        };
+        let config = Config::new().key_case(KeyCase::Camel);
+        assert_eq!(
+            config.to_string(&search).unwrap(),
+            "userName=boxdot&pageSize=20"
+        );
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "sortOrder=asc&maxHitCount=5"
+        );
+        let config = Config::new().key_case(KeyCase::Pascal);
+        assert_eq!(
+            config.to_string(&search).unwrap(),
+            "UserName=boxdot&PageSize=20"
+        );
+        let config = Config::new().key_case(KeyCase::Kebab);
+        assert_eq!(
+            config.to_string(&search).unwrap(),
+            "user-name=boxdot&page-size=20"
+        );
+        let config = Config::new().key_case(KeyCase::ScreamingSnake);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "SORT_ORDER=asc&MAX_HIT_COUNT=5"
+        );
+        // map keys are written unchanged
+        let mut map = BTreeMap::new();
+        map.insert("user_name", "boxdot");
+        let config = Config::new().key_case(KeyCase::Camel);
+        assert_eq!(config.to_string(&map).unwrap(), "user_name=boxdot");
+    }
+
+    #[test]
+    fn test_serialized_len() {
+        use crate::serialized_len;
+
+        #[derive(Serialize)]
+        struct Params {
+            q: &'static str,
+            tags: Vec<&'static str>,
+            page: Option<u32>,
+        }
+
+        let params = Params {
+            q: "a&b c",
+            tags: vec!["x", "y"],
+            page: None,
+        };
+        let len = serialized_len(&params).unwrap();
+        assert_eq!(len, to_string(&params).unwrap().len());
+        let config = Config::new()
+            .array_format(ArrayFormat::Indexed)
+            .extra_params(&[("v", "1")]);
+        assert_eq!(
+            config.serialized_len(&params).unwrap(),
+            config.to_string(&params).unwrap().len()
+        );
+        assert!(serialized_len(&1).is_err());
+    }
+
+    #[test]
+    fn test_none_sentinel() {
+        #[derive(Serialize)]
+        struct Params {
+            q: Option<&'static str>,
+            page: Option<u32>,
+            tags: Vec<Option<&'static str>>,
+        }
+
+        let params = Params {
+            q: Some("rust"),
+            page: None,
+            tags: vec![Some("a"), None],
+        };
+        assert_eq!(to_string(&params).unwrap(), "q=rust&tags=a");
+        let config = Config::new().none_sentinel(Some(String::from("null")));
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=rust&page=null&tags=a&tags=null"
+        );
+        let config = Config::new().none_sentinel(Some(String::from("n/a")));
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=rust&page=n%2Fa&tags=a&tags=n%2Fa"
+        );
+        // a top level `None` has no key to be written with
+        let config = Config::new().none_sentinel(Some(String::from("null")));
+        assert_eq!(config.to_string(&None::<Params>).unwrap(), "");
+    }
+
+    #[test]
+    fn test_map_with_enum_keys() {
+        use std::collections::{BTreeMap, HashMap};
+
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+        enum Selection {
+            A,
+            B,
+        }
+
+        let mut map = BTreeMap::new();
+        map.insert(Selection::B, "b");
+        map.insert(Selection::A, "a");
+        assert_eq!(to_string(&map).unwrap(), "A=a&B=b");
+
+        let mut map = HashMap::new();
+        map.insert(Selection::A, String::from("x y"));
+        map.insert(Selection::B, String::from("z"));
+        let config = Config::new().sort_map_keys(true);
+        assert_eq!(config.to_string(&map).unwrap(), "A=x+y&B=z");
+    }
+
+    #[test]
+    fn test_comma_dedup() {
+        #[derive(Serialize)]
+        struct Params {
+            tags: Vec<&'static str>,
+            ids: Vec<u32>,
+        }
+
+        let params = Params {
+            tags: vec!["a", "a", "b", "a b", "a b"],
+            ids: vec![3, 1, 3],
+        };
+        let config = Config::new().array_format(ArrayFormat::Comma);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "tags=a,a,b,a+b,a+b&ids=3,1,3"
+        );
+        let config = config.comma_dedup(true);
+        assert_eq!(config.to_string(&params).unwrap(), "tags=a,b,a+b&ids=3,1");
+        let config = config.sort_seq_values(true);
+        assert_eq!(config.to_string(&params).unwrap(), "tags=a,a+b,b&ids=1,3");
+        // other array formats are not affected
+        let config = Config::new().comma_dedup(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "tags=a&tags=a&tags=b&tags=a+b&tags=a+b&ids=3&ids=1&ids=3"
+        );
+    }
+
+    #[test]
+    fn test_utf8_passthrough() {
+        use crate::EncodeSet;
+
+        #[derive(Serialize)]
+        struct Params {
+            q: &'static str,
+            tags: Vec<&'static str>,
+        }
+
+        let params = Params {
+            q: "caf\u{e9}=na\u{ef}ve",
+            tags: vec!["\u{1f980} rust", "a/b"],
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "q=caf%C3%A9%3Dna%C3%AFve&tags=%F0%9F%A6%80+rust&tags=a%2Fb"
+        );
+        let config = Config::new().utf8_passthrough(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=caf\u{e9}%3Dna\u{ef}ve&tags=\u{1f980}+rust&tags=a%2Fb"
+        );
+        let config = config.encode_set(EncodeSet::Unreserved);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=caf\u{e9}%3Dna\u{ef}ve&tags=\u{1f980}%20rust&tags=a%2Fb"
+        );
+    }
+
+    #[test]
+    fn test_to_map() {
+        use crate::to_map;
+
+        #[derive(Serialize)]
+        struct Params {
+            tag: Vec<&'static str>,
+            q: &'static str,
+            page: Option<u32>,
+            #[serde(rename = "tag")]
+            extra_tag: &'static str,
+        }
+
+        let params = Params {
+            tag: vec!["a b", "c&d"],
+            q: "rust",
+            page: None,
+            extra_tag: "e",
+        };
+        let map = to_map(&params).unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["q", "tag"]);
+        assert_eq!(map["q"], ["rust"]);
+        assert_eq!(map["tag"], ["a b", "c&d", "e"]);
+
+        let config = Config::new().array_format(ArrayFormat::Comma);
+        let map = config.to_map(&params).unwrap();
+        assert_eq!(map["tag"], ["a b,c&d", "e"]);
+        assert!(to_map(&()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_skip_variants() {
+        use crate::{UnitVariantFormat, Warning};
+
+        #[derive(Serialize)]
+        enum Sort {
+            #[serde(rename = "none")]
+            Unset,
+            Date,
+            Relevance,
+        }
+        #[derive(Serialize)]
+        struct Params {
+            sort: Sort,
+            then: Vec<Sort>,
+        }
+
+        let params = Params {
+            sort: Sort::Unset,
+            then: vec![Sort::Date, Sort::Unset, Sort::Relevance],
+        };
+        assert_eq!(
+            to_string(&params).unwrap(),
+            "sort=none&then=Date&then=none&then=Relevance"
+        );
+        let config = Config::new().skip_variants(&["none"]);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "then=Date&then=Relevance"
+        );
+        let config = config.unit_variant_format(UnitVariantFormat::KeyWithOne);
+        assert_eq!(config.to_string(&params).unwrap(), "Date=1&Relevance=1");
+        let config = Config::new().skip_variants(&["Unset"]);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "sort=none&then=Date&then=none&then=Relevance"
+        );
+
+        let (_, warnings) = Config::new()
+            .skip_variants(&["none", "Date"])
+            .to_string_with_report(&params)
+            .unwrap();
+        assert_eq!(
+            warnings,
+            [
+                Warning::DroppedVariant(String::from("sort")),
+                Warning::DroppedVariant(String::from("then[0]")),
+                Warning::DroppedVariant(String::from("then[1]")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_keys_and_values() {
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize)]
+        struct Params {
+            #[serde(rename = "filter name")]
+            filter_name: &'static str,
+            #[serde(rename = "a&b")]
+            ids: Vec<u32>,
+        }
+
+        let params = Params {
+            filter_name: "x/y",
+            ids: vec![1, 2],
+        };
+        let config = Config::new().array_format(ArrayFormat::Brackets);
+        let combinations = [
+            (true, true, "filter+name=x%2Fy&a%26b[]=1&a%26b[]=2"),
+            (true, false, "filter+name=x/y&a%26b[]=1&a%26b[]=2"),
+            (false, true, "filter name=x%2Fy&a&b[]=1&a&b[]=2"),
+            (false, false, "filter name=x/y&a&b[]=1&a&b[]=2"),
+        ];
+        for &(keys, values, expected) in &combinations {
+            let config = config.clone().encode_keys(keys).encode_values(values);
+            assert_eq!(config.to_string(&params).unwrap(), expected);
+        }
+
+        // brackets in keys are encoded on request
+        let config = config.encode_extra(b"[]");
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "filter+name=x%2Fy&a%26b%5B%5D=1&a%26b%5B%5D=2"
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert("\u{e9}", "\u{e9}");
+        assert_eq!(to_string(&map).unwrap(), "%C3%A9=%C3%A9");
+        let pairs = Config::new().to_encoded_pairs(&map).unwrap();
+        assert_eq!(pairs, [(String::from("%C3%A9"), String::from("%C3%A9"))]);
+        let pairs = Config::new().to_pairs(&map).unwrap();
+        assert_eq!(pairs, [(String::from("\u{e9}"), String::from("\u{e9}"))]);
+    }
+
+    #[test]
+    fn test_io_error_context() {
+        use crate::Error;
+        use std::io;
+
+        /// Writer failing once more than `limit` bytes are written.
+        struct FailAfter {
+            written: usize,
+            limit: usize,
+        }
+
+        impl io::Write for FailAfter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.written + buf.len() > self.limit {
+                    return Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"));
+                }
+                self.written += buf.len();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        #[derive(Serialize)]
+        struct Params {
+            q: &'static str,
+            page: u32,
+            tags: Vec<&'static str>,
+        }
+
+        let params = Params {
+            q: "rust",
+            page: 2,
+            tags: vec!["a", "b"],
+        };
+        // q=rust&page=2&tags=a&tags=b&v=1
+        let config = Config::new().extra_params(&[("v", "1")]);
+        for &(limit, key) in &[(3, "q"), (8, "page"), (21, "tags[1]"), (28, "v")] {
+            let writer = FailAfter { written: 0, limit };
+            let err = config.to_writer(writer, &params).unwrap_err();
+            assert_eq!(err.to_string(), format!("broken pipe (at {})", key));
+            let source = std::error::Error::source(&err).unwrap();
+            assert!(source.downcast_ref::<std::io::Error>().is_some());
+            match err {
+                Error::Path { path, error } => {
+                    assert_eq!(path, key);
+                    assert!(matches!(*error, Error::Extern(_)));
+                }
+                err => panic!("unexpected error: {:?}", err),
+            }
+        }
+        let writer = FailAfter {
+            written: 0,
+            limit: 31,
+        };
+        assert!(config.to_writer(writer, &params).is_ok());
+    }
+
+    #[test]
+    fn test_multi_valued_map() {
+        use std::collections::HashMap;
+
+        #[derive(Serialize)]
+        struct Params {
+            q: &'static str,
+            filters: HashMap<String, Vec<String>>,
+        }
+
+        let mut filters = HashMap::new();
+        filters.insert(
+            String::from("color"),
+            vec![String::from("red"), String::from("blue")],
+        );
+        filters.insert(String::from("size"), vec![String::from("m")]);
+        filters.insert(String::from("brand"), vec![]);
+        let params = Params {
+            q: "shirt",
+            filters,
+        };
+        let config = Config::new().sort_map_keys(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=shirt&color=red&color=blue&size=m"
+        );
+        assert_eq!(
+            config.to_string(&params.filters).unwrap(),
+            "color=red&color=blue&size=m"
+        );
+        let config = config.array_format(ArrayFormat::Brackets);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "q=shirt&color[]=red&color[]=blue&size[]=m"
+        );
+    }
+
+    #[test]
+    fn test_top_level_map() {
+        use crate::Order;
+        use std::collections::HashMap;
+
+        let mut params = HashMap::new();
+        params.insert(String::from("k2"), String::from("v 2"));
+        params.insert(String::from("k1"), String::from("v1"));
+        params.insert(String::from("k3"), String::new());
+        let query = to_string(&params).unwrap();
+        let mut pairs: Vec<_> = query.split('&').collect();
+        pairs.sort_unstable();
+        assert_eq!(pairs, ["k1=v1", "k2=v+2", "k3="]);
+
+        let config = Config::new().sort_map_keys(true);
+        assert_eq!(config.to_string(&params).unwrap(), "k1=v1&k2=v+2&k3=");
+        let config = Config::new().order(Order::Sorted);
+        assert_eq!(config.to_string(&params).unwrap(), "k1=v1&k2=v+2&k3=");
+        let config = Config::new()
+            .sort_map_keys(true)
+            .extra_params(&[("k0", "x")]);
+        assert_eq!(config.to_string(&params).unwrap(), "k1=v1&k2=v+2&k3=&k0=x");
+        assert_eq!(to_string(&HashMap::<String, String>::new()).unwrap(), "");
+    }
+
+    #[test]
+    fn test_unit_variant_index() {
+        use crate::UnitVariantFormat;
+
+        #[derive(Serialize)]
+        enum Status {
+            Open,
+            #[serde(rename = "in progress")]
+            InProgress,
+            Closed = 10,
+        }
+        #[derive(Serialize)]
+        struct Params {
+            status: Status,
+            any: Vec<Status>,
+        }
+
+        let params = Params {
+            status: Status::Open,
+            any: vec![Status::InProgress, Status::Closed],
+        };
+        let config = Config::new().unit_variant_format(UnitVariantFormat::Index);
+        assert_eq!(config.to_string(&params).unwrap(), "status=0&any=1&any=2");
+        assert_eq!(
+            config
+                .clone()
+                .skip_zero_numbers(true)
+                .to_string(&params)
+                .unwrap(),
+            "status=0&any=1&any=2"
+        );
+        let config = config.unit_variant_index_base(1);
+        assert_eq!(config.to_string(&params).unwrap(), "status=1&any=2&any=3");
+        let config = config
+            .unit_variant_index_base(u32::MAX)
+            .array_format(ArrayFormat::Comma);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "status=4294967295&any=4294967296,4294967297"
+        );
+    }
+
+    #[test]
+    fn test_comma_literal() {
+        #[derive(Serialize)]
+        struct Params {
+            tags: Vec<&'static str>,
+            ids: Vec<u32>,
+        }
+
+        let params = Params {
+            tags: vec!["a,b", "c d"],
+            ids: vec![1, 2, 3],
+        };
+        let config = Config::new().array_format(ArrayFormat::Comma);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "tags=a%2Cb,c+d&ids=1,2,3"
+        );
+        let config = config.comma_literal(false);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "tags=a%2Cb%2Cc+d&ids=1%2C2%2C3"
+        );
+        let pairs = config.to_pairs(&params).unwrap();
+        assert_eq!(pairs[0], (String::from("tags"), String::from("a,b,c d")));
+        let config = config.comma_literal(true);
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "tags=a%2Cb,c+d&ids=1,2,3"
+        );
+    }
+
+    #[test]
+    fn test_key_mapper() {
+        use crate::KeyCase;
+        use std::borrow::Cow;
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Serialize)]
+        struct Params {
+            user_name: &'static str,
+            ids: Vec<u32>,
+        }
+
+        let params = Params {
+            user_name: "a b",
+            ids: vec![1, 2],
+        };
+        let config = Config::new()
+            .key_case(KeyCase::Camel)
+            .array_format(ArrayFormat::Indexed)
+            .key_mapper(|key| Cow::Owned(key.to_uppercase()));
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "USERNAME=a+b&IDS[0]=1&IDS[1]=2"
+        );
+        assert_eq!(
+            config.to_pairs(&params).unwrap()[0],
+            (String::from("USERNAME"), String::from("a b"))
+        );
+
+        // the mapped key is percent-encoded
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&keys);
+        let config = Config::new()
+            .key_mapper(|key| Cow::Owned(format!("f {}", key)))
+            .on_pair(move |key, _| sink.lock().unwrap().push(String::from(key)));
+        assert_eq!(
+            config.to_string(&params).unwrap(),
+            "f+user_name=a+b&f+ids=1&f+ids=2"
+        );
+        assert_eq!(*keys.lock().unwrap(), ["f user_name", "f ids", "f ids"]);
+        let pairs = config.to_encoded_pairs(&params).unwrap();
+        assert_eq!(pairs[0], (String::from("f+user_name"), String::from("a+b")));
+    }
+
+    #[test]
+    fn test_key_mapper_guards() {
+        use crate::KeyCollision;
+        use std::borrow::Cow;
+
+        #[derive(Serialize)]
+        struct Params {
+            a: u32,
+            b: u32,
+        }
+
+        let params = Params { a: 1, b: 2 };
+        fn merge(_: &str) -> Cow<'_, str> {
+            Cow::Borrowed("x")
+        }
+        let config = Config::new().key_mapper(merge);
+        assert_eq!(config.to_string(&params).unwrap(), "x=1&x=2");
+
+        // collisions are detected on the mapped keys
+        let config = Config::new()
+            .key_mapper(merge)
+            .key_collision(KeyCollision::Error);
+        assert_eq!(
+            config.to_string(&params).unwrap_err().to_string(),
+            "duplicate key `x` (at b)"
+        );
+        let config = Config::new().key_mapper(merge).strict(true);
+        assert!(config.to_string(&params).is_err());
+        let config = Config::new()
+            .key_mapper(merge)
+            .key_collision(KeyCollision::FirstWins);
+        assert_eq!(config.to_string(&params).unwrap(), "x=1");
+        let config = Config::new()
+            .key_mapper(merge)
+            .key_collision(KeyCollision::LastWins);
+        assert_eq!(config.to_string(&params).unwrap(), "x=2");
+
+        // keys mapped to the same key are coalesced, whatever the policy
+        for &policy in &[
+            KeyCollision::Allow,
+            KeyCollision::FirstWins,
+            KeyCollision::LastWins,
+            KeyCollision::Error,
+        ] {
+            let config = Config::new()
+                .key_mapper(merge)
+                .coalesce_duplicate_keys(true)
+                .key_collision(policy);
+            assert_eq!(config.to_string(&params).unwrap(), "x=1,2");
+        }
+
+        // the length limit applies to the mapped keys
+        let config = Config::new()
+            .key_mapper(|key| Cow::Owned(format!("prefix_{}", key)))
+            .max_key_len(2);
+        assert!(config.to_string(&params).is_err());
+        let config = Config::new()
+            .key_mapper(|key| Cow::Owned(key[..1].to_owned()))
+            .max_key_len(1);
+        assert_eq!(config.to_string(&params).unwrap(), "a=1&b=2");
+    }
+
+    #[test]
+    fn test_to_string_if() {
+        use crate::to_string_if;
+
+        #[derive(Serialize)]
+        struct Params {
+            q: Option<&'static str>,
+            page: u32,
+        }
+
+        let params = Params {
+            q: Some("a b"),
+            page: 1,
+        };
+        assert_eq!(
+            to_string_if(&params, |p| p.q.is_some()).unwrap(),
+            Some(String::from("q=a+b&page=1"))
+        );
+        assert_eq!(to_string_if(&params, |p| p.page > 1).unwrap(), None);
+        let config = Config::new().encode_set(crate::EncodeSet::Unreserved);
+        assert_eq!(
+            config.to_string_if(&params, |_| true).unwrap(),
+            Some(String::from("q=a%20b&page=1"))
+        );
+        // the predicate is checked before serializing
+        assert_eq!(to_string_if(&1, |_| false).unwrap(), None);
+        assert!(to_string_if(&1, |_| true).is_err());
+    }
+
+    #[test]
+    fn test_empty_output() {
+        use crate::EmptyOutputPolicy;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            q: Option<&'static str>,
+            tags: Vec<&'static str>,
+        }
+
+        let empty = Params {
+            q: None,
+            tags: vec![],
+        };
+        let params = Params {
+            q: Some("rust"),
+            tags: vec![],
+        };
+
+        let config = Config::new().empty_output(EmptyOutputPolicy::Ok);
+        assert_eq!(config.to_string(&empty).unwrap(), "");
+        assert_eq!(config.to_string(&params).unwrap(), "q=rust");
+
+        let config = Config::new().empty_output(EmptyOutputPolicy::Error);
+        assert_eq!(
+            config.to_string(&empty).unwrap_err().to_string(),
+            "no parameters to serialize"
+        );
+        assert!(config.to_pairs(&empty).is_err());
+        assert_eq!(config.to_string(&params).unwrap(), "q=rust");
+        let config = config.extra_params(&[("v", "1")]);
+        assert_eq!(config.to_string(&empty).unwrap(), "v=1");
+    }
+
+    #[test]
+    fn test_char_keyed_map() {
+        use std::collections::BTreeMap;
+
+        let mut params = BTreeMap::new();
+        params.insert('a', 1);
+        params.insert('&', 2);
+        params.insert('é', 3);
+        assert_eq!(to_string(&params).unwrap(), "%26=2&a=1&%C3%A9=3");
+
+        let config = Config::new().encode_keys(false);
+        assert_eq!(config.to_string(&params).unwrap(), "&=2&a=1&é=3");
+    }
+
+    #[test]
+    fn test_current_key() {
+        use crate::Serializer;
+        use serde::ser::{SerializeMap, Serializer as _};
+
+        let config = Config::new().array_format(ArrayFormat::Indexed);
+        let mut ser = Serializer::new_with_config(Vec::new(), config);
+        let mut map = (&mut ser).serialize_map(None).unwrap();
+        map.serialize_key("a b").unwrap();
+        assert_eq!(map.current_key(), Some("a b"));
+        map.serialize_value(&1).unwrap();
+        map.serialize_entry("ids", &[1]).unwrap();
+        SerializeMap::end(map).unwrap();
+        assert_eq!(ser.current_key(), None);
+        assert_eq!(ser.into_inner(), b"a+b=1&ids[0]=1");
+    }
+
+    #[test]
+    fn test_map_with_non_scalar_keys() {
+        use std::collections::BTreeMap;
+
+        let mut params = BTreeMap::new();
+        params.insert((1, 2), "a");
+        assert!(to_string(&params).is_err());
+    }
+
+    #[test]
+    fn test_on_pair_after_panic() {
+        use std::panic::{self, AssertUnwindSafe};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        #[derive(Debug, Serialize)]
+        struct Params {
+            key: &'static str,
+        }
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&count);
+        let config = Config::new().on_pair(move |_, value| {
+            assert_ne!(value, "panic");
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            config.to_string(&Params { key: "panic" })
+        }));
+        assert!(res.is_err());
+        assert_eq!(config.to_string(&Params { key: "a" }).unwrap(), "key=a");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_seq_key_changed_by_element() {
+        use crate::UnitVariantFormat;
+        use std::borrow::Cow;
+
+        #[derive(Serialize)]
+        enum Color {
+            Red,
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Item {
+            Id(u32),
+            Color(Color),
+        }
+
+        #[derive(Serialize)]
+        struct Params {
+            items: Vec<Item>,
+        }
+
+        let params = Params {
+            items: vec![Item::Id(1), Item::Color(Color::Red), Item::Id(2)],
+        };
+        let config = Config::new()
+            .unit_variant_format(UnitVariantFormat::KeyWithOne)
+            .key_mapper(|key| Cow::Owned(key.to_uppercase()));
+        assert_eq!(config.to_string(&params).unwrap(), "ITEMS=1&RED=1&ITEMS=2");
+    }
+
+    #[cfg(feature = "unstable")]
+    #[test]
+    fn test_static_encoder() {
+        use crate::{EncodeSet, StaticEncoder};
+
+        let values = ["plain", "a b", "caf\u{e9} & *~", "", "[x]=1"];
+        let encoder = StaticEncoder::new();
+        let config = Config::new();
+        for value in values {
+            assert_eq!(encoder.encode(value), config.encode_value(value));
+        }
+        let encoder = encoder.unreserved();
+        let config = config.encode_set(EncodeSet::Unreserved);
+        for value in values {
+            assert_eq!(encoder.encode(value), config.encode_value(value));
+        }
+        assert!(matches!(
+            encoder.form().encode("plain"),
+            std::borrow::Cow::Borrowed("plain")
+        ));
+    }
 }
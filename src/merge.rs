@@ -0,0 +1,104 @@
+//! Merge already serialized URL parameters strings.
+
+use std::collections::HashSet;
+
+/// How parameters of both query strings with the same key are merged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// All parameters are kept, those of `extra` after those of `base`
+    /// (default).
+    #[default]
+    Append,
+    /// Parameters of `extra` whose key occurs in `base` are dropped.
+    KeepBase,
+    /// Parameters of `base` whose key occurs in `extra` are dropped.
+    ReplaceBase,
+}
+
+/// Merge two URL parameters strings into one.
+///
+/// Both strings are parsed and percent-decoded, merged according to `policy`
+/// and percent-encoded again like values serialized by this crate, e.g. with
+/// spaces written as `+`. A leading `?` of either string is ignored. The order
+/// of the parameters is kept: first the remaining ones of `base`, then the
+/// remaining ones of `extra`.
+///
+/// ```rust
+/// use serde_url_params::{merge_queries, MergePolicy};
+///
+/// let base = "?page=1&q=old";
+/// let extra = "q=new+query&sort=asc";
+/// assert_eq!(
+///     merge_queries(base, extra, MergePolicy::ReplaceBase),
+///     "page=1&q=new+query&sort=asc"
+/// );
+/// assert_eq!(
+///     merge_queries(base, extra, MergePolicy::KeepBase),
+///     "page=1&q=old&sort=asc"
+/// );
+/// ```
+pub fn merge_queries(base: &str, extra: &str, policy: MergePolicy) -> String {
+    let base = parse(base);
+    let extra = parse(extra);
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    match policy {
+        MergePolicy::Append => {
+            serializer.extend_pairs(base.iter().chain(extra.iter()));
+        }
+        MergePolicy::KeepBase => {
+            let keys: HashSet<_> = base.iter().map(|(key, _)| key).collect();
+            serializer.extend_pairs(base.iter());
+            serializer.extend_pairs(extra.iter().filter(|(key, _)| !keys.contains(key)));
+        }
+        MergePolicy::ReplaceBase => {
+            let keys: HashSet<_> = extra.iter().map(|(key, _)| key).collect();
+            serializer.extend_pairs(base.iter().filter(|(key, _)| !keys.contains(key)));
+            serializer.extend_pairs(extra.iter());
+        }
+    }
+    serializer.finish()
+}
+
+/// Parses a URL parameters string into decoded key value pairs.
+fn parse(query: &str) -> Vec<(String, String)> {
+    let query = query.strip_prefix('?').unwrap_or(query);
+    url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_queries, MergePolicy};
+
+    #[test]
+    fn test_merge_policies() {
+        let base = "a=1&b=2&a=3";
+        let extra = "a=x&c=y+z";
+        assert_eq!(
+            merge_queries(base, extra, MergePolicy::Append),
+            "a=1&b=2&a=3&a=x&c=y+z"
+        );
+        assert_eq!(
+            merge_queries(base, extra, MergePolicy::KeepBase),
+            "a=1&b=2&a=3&c=y+z"
+        );
+        assert_eq!(
+            merge_queries(base, extra, MergePolicy::ReplaceBase),
+            "b=2&a=x&c=y+z"
+        );
+    }
+
+    #[test]
+    fn test_merge_encoding() {
+        assert_eq!(
+            merge_queries("?k%20ey=a%26b", "", MergePolicy::Append),
+            "k+ey=a%26b"
+        );
+        assert_eq!(merge_queries("", "", MergePolicy::ReplaceBase), "");
+        assert_eq!(
+            merge_queries("", "x=%C3%A4", MergePolicy::KeepBase),
+            "x=%C3%A4"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Decoded URL parameters as an ordered list of key value pairs.
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::convert::Infallible;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+/// Percent-decoded URL parameters, kept in their original order.
+///
+/// Parsed via `FromStr` or `From<&str>` (and thus `TryFrom<&str>`).
+///
+/// This is a lossless representation of a query string, which does not need
+/// Serde, e.g. to inspect or modify a query before sending it. Parsing never
+/// fails: it works like [`from_str`](../de/fn.from_str.html), and malformed
+/// percent-encodings are kept literally. `Display` encodes the parameters again
+/// like values serialized by this crate.
+///
+/// `QueryParams` serializes as a map with repeated keys, so it can be
+/// flattened into a struct via `#[serde(flatten)]`, promoting its parameters
+/// into the parameters of the struct.
+///
+/// ```rust
+/// use serde_url_params::QueryParams;
+///
+/// let mut params: QueryParams = "q=a+b&tag=x&tag=y".parse().unwrap();
+/// assert_eq!(params.get("q"), Some("a b"));
+/// assert_eq!(params.get_all("tag").collect::<Vec<_>>(), ["x", "y"]);
+///
+/// params.insert("q", "c&d");
+/// params.append("page", "2");
+/// assert_eq!(params.to_string(), "q=c%26d&tag=x&tag=y&page=2");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct QueryParams(Vec<(String, String)>);
+
+impl QueryParams {
+    /// Creates empty parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of parameters, counting repeated keys repeatedly.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the first value of the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns all values of the given key in order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns whether the given key occurs.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.iter().any(|(k, _)| k == key)
+    }
+
+    /// Sets the value of the given key.
+    ///
+    /// The first occurrence of the key is replaced and all further ones are
+    /// removed. If the key does not occur, the parameter is appended.
+    pub fn insert<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        match self.0.iter().position(|(k, _)| *k == key) {
+            Some(pos) => {
+                self.0[pos].1 = value.into();
+                let rest = self.0.split_off(pos + 1);
+                self.0.extend(rest.into_iter().filter(|(k, _)| *k != key));
+            }
+            None => self.0.push((key, value.into())),
+        }
+    }
+
+    /// Appends a parameter, even if its key already occurs.
+    pub fn append<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.0.push((key.into(), value.into()));
+    }
+
+    /// Removes all parameters with the given key, and returns their values.
+    pub fn remove(&mut self, key: &str) -> Vec<String> {
+        let (removed, kept) = std::mem::take(&mut self.0)
+            .into_iter()
+            .partition(|(k, _)| k == key);
+        self.0 = kept;
+        removed.into_iter().map(|(_, value)| value).collect()
+    }
+
+    /// Returns an iterator over the key value pairs in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl FromStr for QueryParams {
+    type Err = Infallible;
+
+    /// Parses and percent-decodes the parameters. A leading `?` is ignored.
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(query))
+    }
+}
+
+impl From<&str> for QueryParams {
+    fn from(query: &str) -> Self {
+        let query = query.strip_prefix('?').unwrap_or(query);
+        QueryParams(
+            url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for QueryParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+        serializer.extend_pairs(self.iter());
+        f.write_str(&serializer.finish())
+    }
+}
+
+impl Serialize for QueryParams {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.iter() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for QueryParams
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        QueryParams(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl IntoIterator for QueryParams {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl From<QueryParams> for Vec<(String, String)> {
+    fn from(params: QueryParams) -> Self {
+        params.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryParams;
+
+    #[test]
+    fn test_round_trip() {
+        for query in &[
+            "",
+            "a=1",
+            "a=1&b=x+y&a=2",
+            "k%26=%3D&%C3%A4=%F0%9F%A6%80",
+            "flag=",
+        ] {
+            let params: QueryParams = query.parse().unwrap();
+            assert_eq!(params.to_string(), *query);
+        }
+        let params = QueryParams::from("?a=%20&flag&a");
+        assert_eq!(
+            params.iter().collect::<Vec<_>>(),
+            [("a", " "), ("flag", ""), ("a", "")]
+        );
+        assert_eq!(params.to_string(), "a=+&flag=&a=");
+    }
+
+    #[test]
+    fn test_modify() {
+        let mut params: QueryParams =
+            vec![("a", "1"), ("b", "2"), ("a", "3"), ("c", "4"), ("a", "5")]
+                .into_iter()
+                .collect();
+        assert_eq!(params.len(), 5);
+        assert_eq!(params.get("a"), Some("1"));
+        assert_eq!(params.get("x"), None);
+        assert!(params.contains_key("c"));
+
+        params.insert("a", "x");
+        assert_eq!(params.to_string(), "a=x&b=2&c=4");
+        params.insert("d", "y");
+        params.append("b", "z");
+        assert_eq!(params.get_all("b").collect::<Vec<_>>(), ["2", "z"]);
+        assert_eq!(params.remove("b"), ["2", "z"]);
+        assert_eq!(params.to_string(), "a=x&c=4&d=y");
+        assert!(params.remove("b").is_empty());
+        assert!(!QueryParams::new().contains_key("a"));
+        assert!(QueryParams::new().is_empty());
+    }
+}
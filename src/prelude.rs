@@ -0,0 +1,44 @@
+//! Convenience re-exports of the commonly used items.
+//!
+//! ```rust
+//! use serde::Serialize;
+//! use serde_url_params::prelude::*;
+//!
+//! #[derive(Serialize)]
+//! struct Search {
+//!     q: &'static str,
+//!     tags: CommaSeparated<Vec<&'static str>>,
+//!     email: NullIfNone<&'static str>,
+//! }
+//!
+//! let search = Search {
+//!     q: "rust",
+//!     tags: CommaSeparated(vec!["a", "b"]),
+//!     email: None.into(),
+//! };
+//! assert_eq!(
+//!     to_string(&search).unwrap(),
+//!     "q=rust&tags=a%2Cb&email=null"
+//! );
+//! ```
+
+pub use crate::config::{
+    ArrayFormat, BoolFormat, BracketContent, BytesFormat, Config, EmptyOutputPolicy, EncodeSet,
+    KeyCase, KeyCollision, NewlinePolicy, NewtypeVariantFormat, Order, TopLevelPolicy, UnitFormat,
+    UnitVariantFormat,
+};
+pub use crate::de::from_str;
+pub use crate::diff::DiffSerialize;
+#[cfg(feature = "bitflags")]
+pub use crate::helpers::bitflag_names;
+#[cfg(feature = "time")]
+pub use crate::helpers::time_rfc3339;
+pub use crate::helpers::{
+    bytes, comma_separated, display_str, duration, duration_micros, duration_millis,
+    duration_nanos, duration_secs, each, fixed_decimals, flatten_or_marker, iso8601_duration,
+    keyed_seq, kv_seq, map_variant, nested_query, nested_query_with, opt_comma_separated,
+    option_as_null, pipe_separated, range, range_brackets, range_underscore, semicolon_separated,
+    space_separated, Bracketed, CommaList, CommaSeparated, Flattened, KeyValue, NullIfNone,
+    OmitIfNone, PreEncoded, Raw, Repeated, SpaceSeparated,
+};
+pub use crate::ser::to_string;
@@ -1,26 +1,1131 @@
 //! Serialize a Rust data structure into URL parameters string.
 
+use crate::config::{
+    ArrayFormat, BoolFormat, BracketContent, BytesFormat, Config, EmptyOutputPolicy, KeyCollision,
+    NewtypeVariantFormat, Order, TopLevelPolicy, UnitFormat, UnitVariantFormat,
+};
 use crate::error::{Error, Result};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
-use std::io;
+use std::io::{self, Write};
+use std::ops::Range;
 
 /// A structure for serializing Rust values into URL parameters string.
 pub struct Serializer<W> {
-    writer: W,
+    writer: Counter<W>,
+    config: Config,
     current_key: Option<String>,
+    /// Incremented on each change of the current key.
+    key_version: usize,
+    /// The current key as written, resolved when writing a parameter.
+    resolved_key: ResolvedKey,
+    /// Version of the current key `resolved_key` was resolved for.
+    resolved_version: Option<usize>,
     first_param: bool,
+    /// Already encoded prefix for values, e.g. `Ok:` for tagged variants.
+    value_prefix: String,
+    /// Keys to restore when the currently serialized structs end.
+    outer_keys: Vec<Option<String>>,
+    /// Whether the keys of the next map are suffixes of the current key.
+    suffixed: bool,
+    /// Prefix of the keys of each currently serialized map.
+    key_prefixes: Vec<Option<String>>,
+    /// Keys of the currently serialized sequences.
+    seq_keys: Vec<Option<String>>,
+    /// Path of the value currently being serialized, used in errors.
+    path: Vec<Segment>,
+    /// Identifies the struct field or map entry currently being serialized.
+    origin: usize,
+    /// Emitted keys with the origin they were emitted from, if key collisions
+    /// are checked.
+    emitted: HashMap<String, usize>,
+    /// Last origin of each key, determined by a dry run for
+    /// `KeyCollision::LastWins`.
+    last_origins: Option<HashMap<String, usize>>,
+    /// Buffered elements of the sequence currently being sorted.
+    sorted_seq: Option<SortedSeq>,
+    /// Buffered entries of the maps and structs currently being reordered.
+    reordered_maps: Vec<ReorderedMap>,
+    /// Buffered values of the sequence currently being joined into one value.
+    joined_seq: Option<JoinedSeq>,
+    spans: Option<Vec<Span>>,
+    /// Number of spans and of parameters before output started to be
+    /// buffered.
+    buffer_start: (usize, usize),
+    warnings: Option<Vec<Warning>>,
+    /// Collected key value pairs, e.g. for `to_form_urlencoded`.
+    pairs: Option<Vec<(String, String)>>,
+    /// Whether the pairs are collected encoded as written, or decoded.
+    encoded_pairs: bool,
+    /// Buffered parameters grouped by key, if duplicate keys are coalesced.
+    coalesced: Option<Coalesced>,
+    /// Number of parameters written or buffered so far.
+    params: usize,
+    /// Outcome of each top level field, if requested.
+    explanations: Option<Vec<Explanation>>,
+}
+
+/// The current key after applying the key mapper, and after percent-encoding
+/// it in addition.
+///
+/// Each step which leaves the key unchanged is `None`, so the common case of
+/// a key written as is does not allocate.
+#[derive(Default)]
+struct ResolvedKey {
+    mapped: Option<String>,
+    encoded: Option<String>,
+}
+
+impl ResolvedKey {
+    /// Resolves `key` by applying the key mapper and percent-encoding.
+    fn new(config: &Config, key: &str) -> Self {
+        fn changed(value: Cow<str>, original: &str) -> Option<String> {
+            match value {
+                Cow::Borrowed(value)
+                    if value.as_ptr() == original.as_ptr() && value.len() == original.len() =>
+                {
+                    None
+                }
+                value => Some(value.into_owned()),
+            }
+        }
+        let mapped = config.map_key(key);
+        let encoded = changed(config.encode_key(&mapped), &mapped);
+        let mapped = changed(mapped, key);
+        ResolvedKey { mapped, encoded }
+    }
+
+    /// Returns the mapped key, given the unmapped `key`.
+    fn mapped<'a>(&'a self, key: &'a str) -> &'a str {
+        self.mapped.as_deref().unwrap_or(key)
+    }
+
+    /// Returns the key as written, given the unmapped `key`.
+    fn encoded<'a>(&'a self, key: &'a str) -> &'a str {
+        match self.encoded {
+            Some(ref encoded) => encoded,
+            None => self.mapped(key),
+        }
+    }
+}
+
+/// Captured output, starting with `&`, and its spans relative to the start of
+/// the output.
+type Captured = (Vec<u8>, Vec<Span>);
+
+/// Sequence whose elements are buffered in order to be written sorted.
+struct SortedSeq {
+    /// Nesting depth of the sequence.
+    depth: usize,
+    /// Whether no parameter was written before the sequence.
+    first_param: bool,
+    /// Output of each element.
+    elements: Vec<Captured>,
+}
+
+/// Map or struct whose entries are buffered in order to be written sorted by
+/// key or reversed.
+struct ReorderedMap {
+    /// Whether no parameter was written before the map.
+    first_param: bool,
+    /// Key and output of each entry, and the number of coalesced groups it
+    /// started.
+    entries: Vec<(String, Captured, usize)>,
+}
+
+/// Segment of the path to a serialized value.
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Key of an emitted parameter together with its byte range in the output.
+pub type Span = (String, Range<usize>);
+
+/// Value which was silently dropped during serialization.
+///
+/// Returned by [`to_string_with_report`] to help diagnosing why a parameter
+/// is missing from the output. Each variant holds the path of the dropped
+/// value, e.g. `filter` or `options.actors[1]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A `None` value.
+    DroppedNone(String),
+    /// A sequence without elements.
+    DroppedEmptySequence(String),
+    /// A unit value, see [`UnitFormat::Omit`].
+    DroppedUnit(String),
+    /// A `false` value, see [`BoolFormat::FlagIfTrue`] and
+    /// [`BoolFormat::OmitIfFalse`].
+    DroppedFalse(String),
+    /// A numeric value equal to zero, see [`Config::skip_zero_numbers`].
+    DroppedZero(String),
+    /// A parameter whose key was already emitted by another struct field or
+    /// map entry, see [`Config::key_collision`].
+    DroppedDuplicateKey(String),
+    /// A unit variant listed in [`Config::skip_variants`].
+    DroppedVariant(String),
+}
+
+/// What happened to a top level struct field or map entry.
+///
+/// Returned by [`to_string_explained`] to help diagnosing why a parameter is
+/// missing from the output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FieldOutcome {
+    /// At least one parameter was written.
+    Emitted,
+    /// The field was skipped by `#[serde(skip_serializing_if = "...")]`.
+    SkippedByPredicate,
+    /// The value was dropped by the serializer, for the given reason.
+    Dropped(Warning),
+    /// The value has nothing to emit for another reason, e.g. an empty raw
+    /// fragment.
+    Empty,
+}
+
+impl fmt::Display for FieldOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FieldOutcome::Emitted => f.write_str("emitted"),
+            FieldOutcome::SkippedByPredicate => f.write_str("skipped by predicate"),
+            FieldOutcome::Dropped(ref warning) => write!(f, "{}", warning),
+            FieldOutcome::Empty => f.write_str("nothing to emit"),
+        }
+    }
+}
+
+/// Key of a top level struct field or map entry together with its outcome.
+pub type Explanation = (String, FieldOutcome);
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::DroppedNone(ref path) => write!(f, "dropped none value `{}`", path),
+            Warning::DroppedEmptySequence(ref path) => {
+                write!(f, "dropped empty sequence `{}`", path)
+            }
+            Warning::DroppedUnit(ref path) => write!(f, "dropped unit value `{}`", path),
+            Warning::DroppedFalse(ref path) => write!(f, "dropped false flag `{}`", path),
+            Warning::DroppedZero(ref path) => write!(f, "dropped zero number `{}`", path),
+            Warning::DroppedDuplicateKey(ref path) => {
+                write!(f, "dropped duplicate key `{}`", path)
+            }
+            Warning::DroppedVariant(ref path) => write!(f, "dropped skipped variant `{}`", path),
+        }
+    }
+}
+
+/// Sequence whose element values are joined into a single value.
+struct JoinedSeq {
+    /// Nesting depth of the sequence.
+    depth: usize,
+    /// Encoded and decoded values of the elements.
+    values: Vec<(String, String)>,
+    /// Value prefix outside of the sequence, which is written once.
+    value_prefix: String,
+}
+
+/// Parameters grouped by key in order of the key's first occurrence.
+#[derive(Default)]
+struct Coalesced {
+    /// Position of each mapped key in `groups`.
+    index: HashMap<String, usize>,
+    groups: Vec<CoalescedGroup>,
+}
+
+impl Coalesced {
+    /// Appends groups taken out before, updating the positions of their keys.
+    fn extend(&mut self, groups: Vec<CoalescedGroup>) {
+        for group in groups {
+            if let CoalescedGroup::Key(ref group) = group {
+                self.index.insert(group.mapped.clone(), self.groups.len());
+            }
+            self.groups.push(group);
+        }
+    }
+}
+
+/// Buffered parameters of a single key, or a raw fragment.
+enum CoalescedGroup {
+    Key(CoalescedKey),
+    Raw(String),
+}
+
+/// Parameters of a single key.
+struct CoalescedKey {
+    key: String,
+    /// The key after applying the key mapper.
+    mapped: String,
+    /// Origin of the first parameter.
+    origin: usize,
+    /// Whether the parameters stem from different origins.
+    duplicate: bool,
+    /// Encoded and decoded value of each parameter, `None` for a flag.
+    values: Vec<Option<(String, String)>>,
+}
+
+/// Writer adaptor counting the number of bytes written so far.
+///
+/// While capturing, the output is written into the innermost capture buffer
+/// instead.
+struct Counter<W> {
+    inner: W,
+    count: usize,
+    captures: Vec<Vec<u8>>,
+}
+
+impl<W> Counter<W> {
+    /// Position in the output resp. in the innermost capture buffer.
+    fn position(&self) -> usize {
+        match self.captures.last() {
+            Some(buf) => buf.len(),
+            None => self.count,
+        }
+    }
+}
+
+impl<W: io::Write> io::Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(capture) = self.captures.last_mut() {
+            capture.extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+        let n = self.inner.write(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 impl<W> Serializer<W>
 where
     W: io::Write,
 {
-    fn new(writer: W) -> Self {
+    /// Creates a serializer writing into `writer` with the default
+    /// configuration.
+    pub fn new(writer: W) -> Self {
+        Self::new_with_config(writer, Config::default())
+    }
+
+    /// Creates a serializer writing into `writer` with the given
+    /// configuration.
+    ///
+    /// This gives direct access to the serializer, e.g. to serialize several
+    /// values one after another into the same stream. Call
+    /// [`finish`](#method.finish) after serializing the last value.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, Serializer};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Page {
+    ///     page: u32,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Search {
+    ///     q: &'static str,
+    /// }
+    ///
+    /// let config = Config::new().extra_params(&[("v", "2")]);
+    /// let mut ser = Serializer::new_with_config(Vec::new(), config);
+    /// Search { q: "rust" }.serialize(&mut ser).unwrap();
+    /// Page { page: 3 }.serialize(&mut ser).unwrap();
+    /// ser.finish().unwrap();
+    /// assert_eq!(ser.into_inner(), b"q=rust&page=3&v=2");
+    /// ```
+    ///
+    /// Note that [`KeyCollision::LastWins`] needs to serialize each value
+    /// twice, so it is only applied if [`prepare`](#method.prepare) is called
+    /// before serializing each value. Otherwise, all parameters are written.
+    pub fn new_with_config(writer: W, config: Config) -> Self {
+        let coalesced = if config.coalesce_duplicate_keys {
+            Some(Coalesced::default())
+        } else {
+            None
+        };
         Serializer {
-            writer,
+            writer: Counter {
+                inner: writer,
+                count: 0,
+                captures: Vec::new(),
+            },
+            config,
             current_key: None,
+            key_version: 0,
+            resolved_key: ResolvedKey::default(),
+            resolved_version: None,
             first_param: true,
+            value_prefix: String::new(),
+            outer_keys: Vec::new(),
+            suffixed: false,
+            key_prefixes: Vec::new(),
+            seq_keys: Vec::new(),
+            path: Vec::new(),
+            origin: 0,
+            emitted: HashMap::new(),
+            last_origins: None,
+            sorted_seq: None,
+            reordered_maps: Vec::new(),
+            joined_seq: None,
+            spans: None,
+            buffer_start: (0, 0),
+            warnings: None,
+            pairs: None,
+            encoded_pairs: false,
+            coalesced,
+            params: 0,
+            explanations: None,
+        }
+    }
+
+    /// Sets the current key, and returns the previous one.
+    fn set_key(&mut self, key: Option<String>) -> Option<String> {
+        self.key_version = self.key_version.wrapping_add(1);
+        std::mem::replace(&mut self.current_key, key)
+    }
+
+    /// Returns whether a parameter was written or buffered so far.
+    pub(crate) fn has_params(&self) -> bool {
+        self.params > 0
+    }
+
+    /// Returns the key of the value being serialized, if any.
+    ///
+    /// This is only useful for hand-written serialization flows holding the
+    /// serializer directly, since a `Serialize` implementation only sees a
+    /// generic serializer. The key is the one given by the serialized data,
+    /// e.g. with the index of a sequence element, but before applying the key
+    /// mapper and percent-encoding.
+    ///
+    /// ```rust
+    /// use serde::ser::{SerializeMap, Serializer as _};
+    /// use serde_url_params::Serializer;
+    ///
+    /// let mut ser = Serializer::new(Vec::new());
+    /// let mut map = (&mut ser).serialize_map(None).unwrap();
+    /// assert_eq!(map.current_key(), None);
+    /// map.serialize_key("page").unwrap();
+    /// assert_eq!(map.current_key(), Some("page"));
+    /// map.serialize_value(&3).unwrap();
+    /// SerializeMap::end(map).unwrap();
+    /// assert_eq!(ser.into_inner(), b"page=3");
+    /// ```
+    pub fn current_key(&self) -> Option<&str> {
+        self.current_key.as_deref()
+    }
+
+    /// Discards the state left over by a value whose serialization failed, so
+    /// that the next value is serialized from the top level again.
+    ///
+    /// Output buffered to be reordered, sorted or joined is discarded.
+    pub(crate) fn reset(&mut self) {
+        if self.is_buffering() {
+            let (spans_len, params) = self.buffer_start;
+            if let Some(ref mut spans) = self.spans {
+                spans.truncate(spans_len);
+            }
+            self.params = params;
         }
+        self.set_key(None);
+        self.value_prefix.clear();
+        self.outer_keys.clear();
+        self.suffixed = false;
+        self.key_prefixes.clear();
+        self.seq_keys.clear();
+        self.path.clear();
+        self.sorted_seq = None;
+        self.reordered_maps.clear();
+        self.joined_seq = None;
+        self.writer.captures.clear();
+        self.first_param = self.writer.count == 0;
+    }
+
+    /// Unwraps the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer.inner
+    }
+
+    /// Prepares serializing `value` into this serializer.
+    ///
+    /// For [`KeyCollision::LastWins`], the value is serialized once without
+    /// output to find out which struct field or map entry emits each key last.
+    /// Call this right before serializing each value into a serializer created
+    /// by [`new_with_config`](#method.new_with_config); for other policies, it
+    /// does nothing. The policy applies within each value and to the extra
+    /// parameters; keys written by values serialized before are kept.
+    ///
+    /// ```rust
+    /// use serde::Serialize;
+    /// use serde_url_params::{Config, KeyCollision, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Params {
+    ///     id: u32,
+    ///     #[serde(flatten)]
+    ///     extra: BTreeMap<&'static str, u32>,
+    /// }
+    ///
+    /// let mut extra = BTreeMap::new();
+    /// extra.insert("id", 2);
+    /// let params = Params { id: 1, extra };
+    /// let config = Config::new().key_collision(KeyCollision::LastWins);
+    /// let mut ser = Serializer::new_with_config(Vec::new(), config);
+    /// ser.prepare(&params).unwrap();
+    /// params.serialize(&mut ser).unwrap();
+    /// ser.finish().unwrap();
+    /// assert_eq!(ser.into_inner(), b"id=2");
+    /// ```
+    pub fn prepare<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        if self.config.key_collision == KeyCollision::LastWins && !self.config.strict {
+            let mut config = self.config.clone();
+            config.on_pair = None;
+            // Whether the output is empty is only known at the very end.
+            config.empty_output = EmptyOutputPolicy::Ok;
+            let mut dry_run = Serializer::new_with_config(io::sink(), config);
+            // Continue the origins, so they match when serializing for real.
+            dry_run.origin = self.origin;
+            value.serialize(&mut dry_run)?;
+            dry_run.finish()?;
+            self.last_origins = Some(dry_run.emitted);
+        }
+        Ok(())
+    }
+
+    /// Finishes serialization by appending the configured extra parameters and
+    /// trailing separator, and flushing the underlying writer.
+    ///
+    /// This must be called at most once.
+    pub fn finish(&mut self) -> Result<()> {
+        use serde::ser::Error;
+        use serde::Serializer;
+        let extra_params = std::mem::take(&mut self.config.extra_params);
+        for (key, value) in &extra_params {
+            self.origin += 1;
+            self.set_key(Some(key.clone()));
+            self.path.push(Segment::Key(key.clone()));
+            let res = self.serialize_str(value).map_err(|err| self.annotate(err));
+            self.path.pop();
+            res?;
+        }
+        self.write_coalesced()?;
+        self.set_key(None);
+        if self.config.empty_output == EmptyOutputPolicy::Error && self.first_param {
+            return Err(Error::custom("no parameters to serialize"));
+        }
+        if self.config.trailing_separator && !self.first_param {
+            self.writer.write_all(b"&")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Buffers a parameter of the current key, if duplicate keys are
+    /// coalesced.
+    ///
+    /// Parameters are grouped by the mapped key, so that keys mapped to the
+    /// same key are coalesced as well.
+    ///
+    /// Returns whether the parameter was buffered.
+    fn coalesce(&mut self, value: Option<(String, String)>) -> bool {
+        if self.coalesced.is_none() {
+            return false;
+        }
+        self.resolve_key();
+        let (coalesced, key) = match (self.coalesced.as_mut(), self.current_key.as_ref()) {
+            (Some(coalesced), Some(key)) => (coalesced, key),
+            _ => return false,
+        };
+        let mapped = String::from(self.resolved_key.mapped(key));
+        match coalesced.index.get(&mapped) {
+            Some(&i) => {
+                if let CoalescedGroup::Key(ref mut group) = coalesced.groups[i] {
+                    group.duplicate |= group.origin != self.origin;
+                    group.values.push(value);
+                }
+            }
+            None => {
+                coalesced
+                    .index
+                    .insert(mapped.clone(), coalesced.groups.len());
+                coalesced.groups.push(CoalescedGroup::Key(CoalescedKey {
+                    key: key.clone(),
+                    mapped,
+                    origin: self.origin,
+                    duplicate: false,
+                    values: vec![value],
+                }));
+            }
+        }
+        self.params += 1;
+        true
+    }
+
+    /// Writes the buffered parameters, coalescing the values of duplicate
+    /// keys.
+    fn write_coalesced(&mut self) -> Result<()> {
+        let coalesced = match self.coalesced.take() {
+            Some(coalesced) => coalesced,
+            None => return Ok(()),
+        };
+        for group in coalesced.groups {
+            let group = match group {
+                CoalescedGroup::Key(group) => group,
+                CoalescedGroup::Raw(fragment) => {
+                    self.write_raw(&fragment)?;
+                    continue;
+                }
+            };
+            self.origin += 1;
+            self.set_key(Some(group.key));
+            if group.duplicate {
+                let (encoded, decoded): (Vec<_>, Vec<_>) = group
+                    .values
+                    .into_iter()
+                    .map(Option::unwrap_or_default)
+                    .unzip();
+                self.write_pair(encoded.join(","), Some(&decoded.join(",")))?;
+            } else {
+                for value in group.values {
+                    match value {
+                        Some((encoded, decoded)) => self.write_pair(encoded, Some(&decoded))?,
+                        None => self.write_flag()?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes a struct field or map value under the given key.
+    fn serialize_keyed<T>(&mut self, key: String, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        self.origin += 1;
+        let explained = self.explanations.is_some() && self.path.is_empty();
+        let params = self.params;
+        let warnings = self.warnings.as_ref().map_or(0, Vec::len);
+        self.path.push(Segment::Key(key));
+        let res = value
+            .serialize(&mut *self)
+            .map_err(|err| self.annotate(err));
+        if explained {
+            let outcome = if self.params > params {
+                FieldOutcome::Emitted
+            } else {
+                match self.warnings.as_ref().and_then(|w| w.get(warnings)) {
+                    Some(warning) => FieldOutcome::Dropped(warning.clone()),
+                    None => FieldOutcome::Empty,
+                }
+            };
+            self.explain(outcome);
+        }
+        self.path.pop();
+        res
+    }
+
+    /// Records the outcome of the top level field at the current path.
+    fn explain(&mut self, outcome: FieldOutcome) {
+        let key = self.path_string();
+        if let Some(ref mut explanations) = self.explanations {
+            explanations.push((key, outcome));
+        }
+    }
+
+    /// Records a top level struct field skipped by its `skip_serializing_if`
+    /// predicate.
+    fn skip_field(&mut self, key: &'static str) {
+        if self.explanations.is_some() && self.path.is_empty() {
+            let key = self.field_key(key);
+            self.path.push(Segment::Key(key));
+            self.explain(FieldOutcome::SkippedByPredicate);
+            self.path.pop();
+        }
+    }
+
+    /// The key of a struct field, converted to the configured case.
+    fn field_key(&self, key: &'static str) -> String {
+        self.config.key_case.apply(key).into_owned()
+    }
+
+    /// Whether the entries of maps resp. structs are buffered for reordering.
+    fn is_reordered(&self, map: bool) -> bool {
+        self.config.order != Order::Declaration || (map && self.config.sort_map_keys)
+    }
+
+    fn begin_entries(&mut self, map: bool) {
+        if self.is_reordered(map) {
+            self.begin_buffering();
+            self.reordered_maps.push(ReorderedMap {
+                first_param: self.first_param,
+                entries: Vec::new(),
+            });
+        }
+    }
+
+    /// Serializes a struct field or map entry, buffering its output if the
+    /// entries are reordered.
+    fn serialize_ordered<T>(&mut self, key: String, value: &T, map: bool) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        if !self.is_reordered(map) {
+            return self.serialize_keyed(key, value);
+        }
+        let groups = self.coalesced_groups();
+        let spans_len = self.begin_capture();
+        let res = self.serialize_keyed(key.clone(), value);
+        let captured = self.end_capture(spans_len);
+        let groups = self.coalesced_groups() - groups;
+        if let Some(map) = self.reordered_maps.last_mut() {
+            map.entries.push((key, captured, groups));
+        }
+        res
+    }
+
+    /// Number of groups of coalesced parameters buffered so far.
+    fn coalesced_groups(&self) -> usize {
+        self.coalesced
+            .as_ref()
+            .map_or(0, |coalesced| coalesced.groups.len())
+    }
+
+    fn end_entries(&mut self, map: bool) -> Result<()> {
+        if !self.is_reordered(map) {
+            return Ok(());
+        }
+        if let Some(buffered) = self.reordered_maps.pop() {
+            // Take out the coalesced groups started by each entry, so they are
+            // reordered along with the entries.
+            let mut groups = match self.coalesced {
+                Some(ref mut coalesced) => {
+                    let count = buffered.entries.iter().map(|entry| entry.2).sum::<usize>();
+                    coalesced.groups.split_off(coalesced.groups.len() - count)
+                }
+                None => Vec::new(),
+            }
+            .into_iter();
+            let mut entries: Vec<_> = buffered
+                .entries
+                .into_iter()
+                .map(|(key, captured, count)| {
+                    (
+                        key,
+                        captured,
+                        groups.by_ref().take(count).collect::<Vec<_>>(),
+                    )
+                })
+                .collect();
+            if self.config.order == Order::Sorted || (map && self.config.sort_map_keys) {
+                // stable, so values of the same key keep their order
+                entries.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+            }
+            if self.config.order == Order::Reverse {
+                entries.reverse();
+            }
+            let mut captured = Vec::with_capacity(entries.len());
+            for (_, output, groups) in entries {
+                captured.push(output);
+                if let Some(ref mut coalesced) = self.coalesced {
+                    coalesced.extend(groups);
+                }
+            }
+            self.write_captured(buffered.first_param, captured)?;
+        }
+        Ok(())
+    }
+
+    fn begin_seq(&mut self) -> Result<()> {
+        if self.config.strict && self.current_key.is_some() {
+            if let Some(Some(_)) = self.seq_keys.last() {
+                return Err(self.annotate(Error::unsupported("nested sequence in strict mode")));
+            }
+        }
+        if self.config.sort_seq_values
+            && self.config.array_format == ArrayFormat::Repeated
+            && self.current_key.is_some()
+            && self.sorted_seq.is_none()
+        {
+            self.begin_buffering();
+            self.sorted_seq = Some(SortedSeq {
+                depth: self.seq_keys.len() + 1,
+                first_param: self.first_param,
+                elements: Vec::new(),
+            });
+        }
+        if self.config.array_format == ArrayFormat::Comma
+            && self.current_key.is_some()
+            && self.joined_seq.is_none()
+        {
+            self.joined_seq = Some(JoinedSeq {
+                depth: self.seq_keys.len() + 1,
+                values: Vec::new(),
+                value_prefix: std::mem::take(&mut self.value_prefix),
+            });
+        }
+        self.seq_keys.push(self.current_key.clone());
+        self.path.push(Segment::Index(0));
+        Ok(())
+    }
+
+    /// Serializes the next element of a sequence.
+    fn serialize_indexed<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ::serde::ser::Serialize,
+    {
+        use serde::ser::Error;
+        let index = match self.path.last() {
+            Some(Segment::Index(index)) => *index,
+            _ => 0,
+        };
+        if let Some(max_len) = self.config.max_seq_len {
+            if index >= max_len {
+                return Err(self.annotate(Error::custom(format_args!(
+                    "sequence exceeds the maximum length of {}",
+                    max_len
+                ))));
+            }
+        }
+        if let Some(Some(key)) = self.seq_keys.last() {
+            let index = self.config.index_base + index;
+            let key = match self.config.array_format {
+                ArrayFormat::Repeated | ArrayFormat::Comma => None,
+                ArrayFormat::NumberedSuffix => Some(format!("{}{}", key, index)),
+                ArrayFormat::Indexed => Some(format!("{}[{}]", key, index)),
+                ArrayFormat::Brackets => Some(match self.config.bracket_content {
+                    BracketContent::Empty => format!("{}[]", key),
+                    BracketContent::Index => format!("{}[{}]", key, index),
+                    BracketContent::Custom(ref content) => format!("{}[{}]", key, content),
+                }),
+            };
+            if key.is_some() {
+                self.set_key(key);
+            }
+        }
+        let key_version = self.key_version;
+        let sorted = self.is_sorted_seq();
+        let spans_len = if sorted { self.begin_capture() } else { 0 };
+        let res = value
+            .serialize(&mut *self)
+            .map_err(|err| self.annotate(err));
+        if sorted {
+            let captured = self.end_capture(spans_len);
+            if let Some(ref mut seq) = self.sorted_seq {
+                seq.elements.push(captured);
+            }
+        }
+        if let Some(Segment::Index(index)) = self.path.last_mut() {
+            *index += 1;
+        }
+        // Only restore the key if the element changed it, so that the key
+        // stays resolved for the next element in the hot loop of large
+        // sequences.
+        if self.key_version != key_version {
+            if let Some(key) = self.seq_keys.last() {
+                let key = key.clone();
+                self.set_key(key);
+            }
+        }
+        res
+    }
+
+    fn end_seq(&mut self) -> Result<()> {
+        let empty = matches!(
+            (self.seq_keys.last(), self.path.pop()),
+            (Some(Some(_)), Some(Segment::Index(0)))
+        );
+        if empty {
+            if self.config.strict {
+                return Err(self.annotate(Error::unsupported("empty sequence in strict mode")));
+            }
+            self.warn(Warning::DroppedEmptySequence);
+        }
+        if self.is_sorted_seq() {
+            self.write_sorted_seq()?;
+        }
+        if let Some(depth) = self.joined_seq.as_ref().map(|seq| seq.depth) {
+            if depth == self.seq_keys.len() {
+                self.write_joined_seq()?;
+            }
+        }
+        if let Some(key) = self.seq_keys.pop() {
+            self.set_key(key);
+        }
+        Ok(())
+    }
+
+    /// Whether the elements of the current sequence are buffered for sorting.
+    fn is_sorted_seq(&self) -> bool {
+        match self.sorted_seq {
+            Some(ref seq) => seq.depth == self.seq_keys.len(),
+            None => false,
+        }
+    }
+
+    /// Writes the buffered values of the joined sequence as a single value.
+    fn write_joined_seq(&mut self) -> Result<()> {
+        let mut seq = match self.joined_seq.take() {
+            Some(seq) => seq,
+            None => return Ok(()),
+        };
+        self.value_prefix = seq.value_prefix;
+        if seq.values.is_empty() {
+            return Ok(());
+        }
+        if self.config.sort_seq_values {
+            seq.values.sort();
+        }
+        if self.config.comma_dedup {
+            let mut seen = HashSet::new();
+            seq.values
+                .retain(|(encoded, _)| seen.insert(encoded.clone()));
+        }
+        let (encoded, decoded): (Vec<_>, Vec<_>) = seq.values.into_iter().unzip();
+        let separator = if self.config.comma_encoded {
+            "%2C"
+        } else {
+            ","
+        };
+        self.write_pair(encoded.join(separator), Some(&decoded.join(",")))
+    }
+
+    /// Writes the buffered elements of the sorted sequence.
+    fn write_sorted_seq(&mut self) -> Result<()> {
+        let mut seq = match self.sorted_seq.take() {
+            Some(seq) => seq,
+            None => return Ok(()),
+        };
+        seq.elements.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.write_captured(seq.first_param, seq.elements)
+    }
+
+    /// Starts capturing the output, and returns the number of spans recorded
+    /// so far.
+    ///
+    /// The output is captured with a leading `&`, which is dropped again by
+    /// [`write_captured`](#method.write_captured) for the very first
+    /// parameter.
+    fn begin_capture(&mut self) -> usize {
+        self.begin_buffering();
+        self.writer.captures.push(Vec::new());
+        self.first_param = false;
+        self.spans.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Whether output is buffered to be reordered or sorted.
+    fn is_buffering(&self) -> bool {
+        !self.writer.captures.is_empty()
+            || !self.reordered_maps.is_empty()
+            || self.sorted_seq.is_some()
+    }
+
+    /// Records the number of spans and parameters, unless output is buffered
+    /// already.
+    fn begin_buffering(&mut self) {
+        if !self.is_buffering() {
+            self.buffer_start = (self.spans.as_ref().map_or(0, Vec::len), self.params);
+        }
+    }
+
+    /// Stops capturing the output, and returns it with the spans recorded
+    /// since `spans_len`.
+    fn end_capture(&mut self, spans_len: usize) -> Captured {
+        let output = self.writer.captures.pop().unwrap_or_default();
+        let spans = match self.spans {
+            Some(ref mut spans) => spans.split_off(spans_len),
+            None => Vec::new(),
+        };
+        (output, spans)
+    }
+
+    /// Writes captured outputs in the given order.
+    fn write_captured<I>(&mut self, first_param: bool, captured: I) -> Result<()>
+    where
+        I: IntoIterator<Item = Captured>,
+    {
+        self.first_param = first_param;
+        for (output, spans) in captured {
+            if output.is_empty() {
+                continue;
+            }
+            let skip = if self.first_param { 1 } else { 0 };
+            let base = self.writer.position();
+            self.writer.write_all(&output[skip..])?;
+            self.first_param = false;
+            if let Some(ref mut all_spans) = self.spans {
+                all_spans.extend(
+                    spans.into_iter().map(|(key, range)| {
+                        (key, base + range.start - skip..base + range.end - skip)
+                    }),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Annotates the error with the current path, unless it already has one.
+    fn annotate(&self, err: Error) -> Error {
+        if self.path.is_empty() {
+            return err;
+        }
+        match err {
+            Error::Path { .. } => err,
+            err => Error::Path {
+                path: self.path_string(),
+                error: Box::new(err),
+            },
+        }
+    }
+
+    /// Renders the current path, e.g. `options.actors[1]`.
+    fn path_string(&self) -> String {
+        let mut path = String::new();
+        for segment in &self.path {
+            match segment {
+                Segment::Key(key) if path.is_empty() => path.push_str(key),
+                Segment::Key(key) => {
+                    path.push('.');
+                    path.push_str(key);
+                }
+                Segment::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
+    }
+
+    /// Records that the value at the current path was dropped, if a report
+    /// was requested.
+    fn warn(&mut self, warning: fn(String) -> Warning) {
+        if self.warnings.is_some() && self.current_key.is_some() {
+            let path = self.path_string();
+            if let Some(ref mut warnings) = self.warnings {
+                warnings.push(warning(path));
+            }
+        }
+    }
+
+    /// Writes the separator and the current key, but no value.
+    ///
+    /// Returns the offset at which the parameter starts in the output, or
+    /// `None` if the parameter is dropped due to a key collision.
+    #[inline]
+    fn write_key(&mut self) -> Result<Option<usize>> {
+        use serde::ser::Error;
+        if self.current_key.is_none() {
+            return match self.config.top_level_scalar {
+                TopLevelPolicy::Error => Err(Error::custom("cannot serialize top level value")),
+                TopLevelPolicy::Empty => Ok(None),
+            };
+        }
+        self.resolve_key();
+        if let (Some(max_len), Some(key)) = (self.config.max_key_len, self.written_key()) {
+            if key.len() > max_len {
+                return Err(Error::custom(format_args!(
+                    "key of length {} exceeds the maximum length of {}",
+                    key.len(),
+                    max_len
+                )));
+            }
+        }
+        if (self.config.strict || self.config.key_collision != KeyCollision::Allow)
+            && !self.resolve_collision()?
+        {
+            self.warn(Warning::DroppedDuplicateKey);
+            return Ok(None);
+        }
+        if !self.first_param {
+            self.writer.write_all(b"&")?;
+        }
+        let start = self.writer.position();
+        if let Some(ref key) = self.current_key {
+            self.writer
+                .write_all(self.resolved_key.encoded(key).as_bytes())?;
+        }
+        self.first_param = false;
+        self.params += 1;
+        Ok(Some(start))
+    }
+
+    /// Resolves the current key, unless it is resolved already.
+    #[inline]
+    fn resolve_key(&mut self) {
+        // The resolved key is reused as long as the current key is unchanged,
+        // e.g. for the elements of a sequence.
+        if self.resolved_version != Some(self.key_version) {
+            if let Some(ref key) = self.current_key {
+                self.resolved_key = ResolvedKey::new(&self.config, key);
+            }
+            self.resolved_version = Some(self.key_version);
+        }
+    }
+
+    /// Applies the key collision policy to the current key, and returns
+    /// whether the parameter is to be written.
+    fn resolve_collision(&mut self) -> Result<bool> {
+        use serde::ser::Error;
+        let key = self.written_key().unwrap_or_default().to_owned();
+        let policy = if self.config.strict {
+            KeyCollision::Error
+        } else {
+            self.config.key_collision
+        };
+        let previous = self.emitted.get(&key).copied();
+        match (policy, previous) {
+            (KeyCollision::LastWins, _) => match self.last_origins {
+                Some(ref last_origins) => Ok(last_origins.get(&key) == Some(&self.origin)),
+                None => {
+                    // Dry run: record the last origin of each key.
+                    self.emitted.insert(key, self.origin);
+                    Ok(true)
+                }
+            },
+            (_, None) => {
+                self.emitted.insert(key, self.origin);
+                Ok(true)
+            }
+            (_, Some(origin)) if origin == self.origin => Ok(true),
+            (KeyCollision::Error, Some(_)) => Err(self.annotate(Error::custom(format_args!(
+                "duplicate key `{}`{}",
+                key,
+                if self.config.strict {
+                    " in strict mode"
+                } else {
+                    ""
+                }
+            )))),
+            (_, Some(_)) => Ok(false),
+        }
+    }
+
+    /// Writes the current key as a parameter without value.
+    #[inline]
+    fn write_flag(&mut self) -> Result<()> {
+        if let Some(ref mut seq) = self.joined_seq {
+            seq.values.push((String::new(), String::new()));
+            return Ok(());
+        }
+        if self.coalesce(None) {
+            return Ok(());
+        }
+        if let Some(start) = self.write_key()? {
+            self.record_span(start);
+            self.notify_pair("");
+            self.collect_pair(String::new());
+        }
+        Ok(())
     }
 
     #[inline]
@@ -28,25 +1133,142 @@ where
     where
         T: fmt::Display,
     {
-        use serde::ser::Error;
-        match self.current_key.as_ref() {
-            Some(key) => {
-                write!(
-                    self.writer,
-                    "{}{}={}",
-                    if self.first_param { "" } else { "&" },
-                    key,
-                    value
-                )?;
-                self.first_param = false;
-                Ok(())
+        self.write_pair(value, None)
+    }
+
+    /// Writes the current key with the already encoded `value`.
+    ///
+    /// `decoded` is the value before encoding, which is passed to the pair
+    /// callback; if `None`, the value itself is passed.
+    #[inline]
+    fn write_pair<T>(&mut self, value: T, decoded: Option<&str>) -> Result<()>
+    where
+        T: fmt::Display,
+    {
+        if let Some(ref mut seq) = self.joined_seq {
+            let encoded = format!("{}{}", self.value_prefix, value);
+            let decoded = match decoded {
+                Some(decoded) => String::from(decoded),
+                None => value.to_string(),
+            };
+            seq.values.push((encoded, decoded));
+            return Ok(());
+        }
+        if self.coalesced.is_some() && self.current_key.is_some() {
+            let encoded = format!("{}{}", self.value_prefix, value);
+            let decoded = match decoded {
+                Some(decoded) => String::from(decoded),
+                None => value.to_string(),
+            };
+            self.coalesce(Some((encoded, decoded)));
+            return Ok(());
+        }
+        if let Some(start) = self.write_key()? {
+            self.writer.write_all(b"=")?;
+            self.writer.write_all(self.value_prefix.as_bytes())?;
+            write!(self.writer, "{}", value)?;
+            self.record_span(start);
+            if self.encoded_pairs {
+                self.collect_pair(format!("{}{}", self.value_prefix, value));
+            }
+            if self.config.on_pair.is_some() || self.pairs.is_some() && !self.encoded_pairs {
+                let decoded = match decoded {
+                    Some(decoded) => String::from(decoded),
+                    None => value.to_string(),
+                };
+                self.notify_pair(&decoded);
+                if !self.encoded_pairs {
+                    self.collect_pair(decoded);
+                }
             }
-            None => Err(Error::custom("cannot serialize top level value")),
+        }
+        Ok(())
+    }
+
+    /// Collects the current key with the given value, if pairs are collected.
+    fn collect_pair(&mut self, value: String) {
+        if self.pairs.is_none() {
+            return;
+        }
+        let key = if self.encoded_pairs {
+            self.written_key()
+        } else {
+            self.mapped_key()
+        };
+        if let (Some(key), Some(pairs)) = (key.map(String::from), self.pairs.as_mut()) {
+            pairs.push((key, value));
+        }
+    }
+
+    /// Passes the current key and the given value to the pair callback.
+    fn notify_pair(&self, value: &str) {
+        if let (Some(callback), Some(key)) = (&self.config.on_pair, self.mapped_key()) {
+            callback.call(key, value);
+        }
+    }
+
+    /// Returns the current key after applying the key mapper, as resolved
+    /// when writing the current parameter.
+    fn mapped_key(&self) -> Option<&str> {
+        let key = self.current_key.as_deref()?;
+        Some(self.resolved_key.mapped(key))
+    }
+
+    /// Returns the current key as written, as resolved when writing the
+    /// current parameter.
+    fn written_key(&self) -> Option<&str> {
+        let key = self.current_key.as_deref()?;
+        Some(self.resolved_key.encoded(key))
+    }
+
+    /// Writes an already encoded query fragment verbatim.
+    fn write_raw(&mut self, fragment: &str) -> Result<()> {
+        if self.pairs.is_some() {
+            return Err(Error::unsupported("raw fragment"));
+        }
+        if fragment.is_empty() {
+            return Ok(());
+        }
+        if let Some(ref mut coalesced) = self.coalesced {
+            // buffered to keep its position among the coalesced parameters
+            coalesced
+                .groups
+                .push(CoalescedGroup::Raw(String::from(fragment)));
+            self.params += 1;
+            return Ok(());
+        }
+        if !self.first_param {
+            self.writer.write_all(b"&")?;
+        }
+        self.writer.write_all(fragment.as_bytes())?;
+        self.first_param = false;
+        self.params += 1;
+        Ok(())
+    }
+
+    /// Writes a numeric value, unless it is zero and zeros are skipped.
+    #[inline]
+    fn write_number<T>(&mut self, value: T) -> Result<()>
+    where
+        T: fmt::Display + Default + PartialEq,
+    {
+        if self.config.skip_zero_numbers && value == T::default() {
+            self.warn(Warning::DroppedZero);
+            Ok(())
+        } else {
+            self.write_key_value(value)
+        }
+    }
+
+    #[inline]
+    fn record_span(&mut self, start: usize) {
+        if let (Some(spans), Some(key)) = (self.spans.as_mut(), self.current_key.as_ref()) {
+            spans.push((key.clone(), start..self.writer.position()));
         }
     }
 }
 
-impl<'a, W> ::serde::ser::Serializer for &'a mut Serializer<W>
+impl<W> ::serde::ser::Serializer for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -63,74 +1285,105 @@ where
 
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<()> {
-        self.write_key_value(value)
+        match self.config.bool_format {
+            BoolFormat::TrueFalse => self.write_key_value(value),
+            BoolFormat::Numeric => self.write_key_value(if value { 1 } else { 0 }),
+            BoolFormat::YesNo => self.write_key_value(if value { "yes" } else { "no" }),
+            BoolFormat::OnOff => self.write_key_value(if value { "on" } else { "off" }),
+            BoolFormat::FlagIfTrue if value => self.write_flag(),
+            BoolFormat::OmitIfFalse if value => self.write_key_value(value),
+            BoolFormat::FlagIfTrue | BoolFormat::OmitIfFalse => {
+                self.warn(Warning::DroppedFalse);
+                Ok(())
+            }
+        }
     }
 
     #[inline]
     fn serialize_i8(self, value: i8) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_i16(self, value: i16) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_i32(self, value: i32) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_i64(self, value: i64) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_u8(self, value: u8) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_u16(self, value: u16) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_u32(self, value: u32) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_u64(self, value: u64) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
+    }
+
+    #[inline]
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.write_number(value)
+    }
+
+    #[inline]
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_f32(self, value: f32) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_f64(self, value: f64) -> Result<()> {
-        self.write_key_value(value)
+        self.write_number(value)
     }
 
     #[inline]
     fn serialize_char(self, value: char) -> Result<()> {
-        self.write_key_value(value)
+        self.serialize_str(value.encode_utf8(&mut [0; 4]))
     }
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
-        use std::iter::FromIterator;
-        let encoded = String::from_iter(url::form_urlencoded::byte_serialize(value.as_bytes()));
-        self.write_key_value(&encoded)
+        let value = if self.config.trim_values {
+            value.trim_matches(|c: char| c.is_ascii_whitespace())
+        } else {
+            value
+        };
+        let value = self.config.newline_normalization.normalize(value);
+        let encoded = self.config.encode(&value);
+        self.write_pair(&encoded, Some(&value))
     }
 
     #[inline]
     fn serialize_bytes(self, value: &[u8]) -> Result<()> {
         use serde::ser::SerializeSeq;
+        match self.config.bytes_format {
+            BytesFormat::Seq => (),
+            BytesFormat::Base64 => return self.serialize_str(&encode_base64(value)),
+            BytesFormat::Hex => return self.serialize_str(&encode_hex(value)),
+        }
         let mut seq = self.serialize_seq(Some(value.len()))?;
         for byte in value {
             seq.serialize_element(byte)?;
@@ -140,6 +1393,13 @@ where
 
     #[inline]
     fn serialize_none(self) -> Result<()> {
+        if self.current_key.is_some() {
+            if let Some(sentinel) = self.config.none_sentinel.clone() {
+                let encoded = self.config.encode(&sentinel);
+                return self.write_pair(&encoded, Some(&sentinel));
+            }
+        }
+        self.warn(Warning::DroppedNone);
         Ok(())
     }
 
@@ -153,30 +1413,93 @@ where
 
     #[inline]
     fn serialize_unit(self) -> Result<()> {
-        Ok(())
+        if self.current_key.is_none() {
+            return Ok(());
+        }
+        if self.config.strict_units {
+            return Err(Error::unsupported("unit"));
+        }
+        match self.config.unit_format {
+            UnitFormat::Omit => {
+                self.warn(Warning::DroppedUnit);
+                Ok(())
+            }
+            UnitFormat::Flag => self.write_flag(),
+            UnitFormat::EmptyValue => self.write_key_value(""),
+        }
     }
 
     #[inline]
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
-        Ok(())
+        self.serialize_unit()
     }
 
     #[inline]
     fn serialize_unit_variant(
         self,
         _name: &'static str,
-        _variant_index: u32,
+        variant_index: u32,
         variant: &'static str,
     ) -> Result<()> {
         use serde::Serialize;
+        if self.current_key.is_some() && self.config.skip_variants.iter().any(|v| v == variant) {
+            self.warn(Warning::DroppedVariant);
+            return Ok(());
+        }
+        if self.config.unit_variant_format == UnitVariantFormat::KeyWithOne
+            && self.current_key.is_some()
+        {
+            // Written as a parameter of its own, also inside a joined sequence.
+            let joined_seq = self.joined_seq.take();
+            let key = self.set_key(Some(String::from(variant)));
+            let res = self.write_key_value(1);
+            self.set_key(key);
+            self.joined_seq = joined_seq;
+            return res;
+        }
+        if self.config.unit_variant_format == UnitVariantFormat::Index {
+            let index = u64::from(variant_index) + u64::from(self.config.unit_variant_index_base);
+            // not subject to `skip_zero_numbers`, the index is no number field
+            return self.write_key_value(index);
+        }
         variant.serialize(self)
     }
 
     #[inline]
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
+        if name == crate::helpers::RAW {
+            let mut string_serializer = StringOnlySerializer::default();
+            value.serialize(&mut string_serializer)?;
+            return self.write_raw(&string_serializer.value);
+        }
+        if name == crate::helpers::PRE_ENCODED {
+            if self.pairs.is_some() && !self.encoded_pairs {
+                return Err(Error::unsupported("pre-encoded value"));
+            }
+            let value = to_key_string(value)?;
+            return self.write_key_value(&value);
+        }
+        if name == crate::helpers::SUFFIXED {
+            self.suffixed = true;
+            let res = value.serialize(&mut *self);
+            self.suffixed = false;
+            return res;
+        }
+        let array_format = match name {
+            crate::helpers::REPEATED => Some(ArrayFormat::Repeated),
+            crate::helpers::COMMA_LIST => Some(ArrayFormat::Comma),
+            crate::helpers::BRACKETED => Some(ArrayFormat::Brackets),
+            _ => None,
+        };
+        if let Some(array_format) = array_format {
+            let outer = std::mem::replace(&mut self.config.array_format, array_format);
+            let res = value.serialize(&mut *self);
+            self.config.array_format = outer;
+            return res;
+        }
         value.serialize(self)
     }
 
@@ -185,17 +1508,32 @@ where
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<()>
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        value.serialize(self)
+        match self.config.newtype_variant_format {
+            NewtypeVariantFormat::Value => value.serialize(self),
+            NewtypeVariantFormat::TaggedColon => {
+                let len = self.value_prefix.len();
+                let variant = self.config.encode(variant);
+                self.value_prefix.push_str(&variant);
+                match self.config.variant_separator {
+                    Some(ref separator) => self.value_prefix.push_str(separator),
+                    None => self.value_prefix.push(':'),
+                }
+                let res = value.serialize(&mut *self);
+                self.value_prefix.truncate(len);
+                res
+            }
+        }
     }
 
     #[inline]
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.begin_seq()?;
         Ok(self)
     }
 
@@ -208,9 +1546,9 @@ where
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Ok(self)
+        self.serialize_seq(Some(len))
     }
 
     #[inline]
@@ -219,21 +1557,33 @@ where
         _name: &'static str,
         _variant_index: u32,
         _variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Ok(self)
+        self.serialize_seq(Some(len))
     }
 
     #[inline]
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        let prefix = if std::mem::take(&mut self.suffixed) {
+            self.current_key.clone()
+        } else {
+            None
+        };
+        self.key_prefixes.push(prefix);
+        self.outer_keys.push(self.current_key.clone());
+        self.begin_entries(true);
         Ok(self)
     }
 
     #[inline]
-    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
-        if self.current_key.is_some() {
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        // A nested struct without fields has nothing to emit, so it is fine.
+        if self.current_key.is_some() && len != 0 {
             Err(Self::Error::unsupported("nested struct"))
         } else {
+            let key = self.set_key(None);
+            self.outer_keys.push(key);
+            self.begin_entries(false);
             Ok(self)
         }
     }
@@ -244,17 +1594,20 @@ where
         _name: &'static str,
         _variant_index: u32,
         _variant: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        if self.current_key.is_some() {
+        if self.current_key.is_some() && len != 0 {
             Err(Self::Error::unsupported("nexted struct variant"))
         } else {
+            let key = self.set_key(None);
+            self.outer_keys.push(key);
+            self.begin_entries(false);
             Ok(self)
         }
     }
 }
 
-impl<'a, W> ::serde::ser::SerializeSeq for &'a mut Serializer<W>
+impl<W> ::serde::ser::SerializeSeq for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -265,15 +1618,15 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        value.serialize(&mut **self)
+        self.serialize_indexed(value)
     }
 
     fn end(self) -> Result<()> {
-        Ok(())
+        self.end_seq()
     }
 }
 
-impl<'a, W> ::serde::ser::SerializeTuple for &'a mut Serializer<W>
+impl<W> ::serde::ser::SerializeTuple for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -284,15 +1637,15 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        value.serialize(&mut **self)
+        self.serialize_indexed(value)
     }
 
     fn end(self) -> Result<()> {
-        Ok(())
+        self.end_seq()
     }
 }
 
-impl<'a, W> ::serde::ser::SerializeTupleStruct for &'a mut Serializer<W>
+impl<W> ::serde::ser::SerializeTupleStruct for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -303,15 +1656,15 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        value.serialize(&mut **self)
+        self.serialize_indexed(value)
     }
 
     fn end(self) -> Result<()> {
-        Ok(())
+        self.end_seq()
     }
 }
 
-impl<'a, W> ::serde::ser::SerializeTupleVariant for &'a mut Serializer<W>
+impl<W> ::serde::ser::SerializeTupleVariant for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -322,15 +1675,15 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        value.serialize(&mut **self)
+        self.serialize_indexed(value)
     }
 
     fn end(self) -> Result<()> {
-        Ok(())
+        self.end_seq()
     }
 }
 
-impl<'a, W> ::serde::ser::SerializeMap for &'a mut Serializer<W>
+impl<W> ::serde::ser::SerializeMap for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -341,11 +1694,12 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        self.current_key = {
-            let mut string_serializer = StringOnlySerializer::default();
-            key.serialize(&mut string_serializer)?;
-            Some(string_serializer.into())
+        let key = to_key_string(key)?;
+        let key = match self.key_prefixes.last() {
+            Some(Some(prefix)) => format!("{}{}", prefix, key),
+            _ => key,
         };
+        self.set_key(Some(key));
         Ok(())
     }
 
@@ -353,15 +1707,20 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        value.serialize(&mut **self)
+        let key = self.current_key.clone().unwrap_or_default();
+        self.serialize_ordered(key, value, true)
     }
 
     fn end(self) -> Result<()> {
+        self.end_entries(true)?;
+        self.key_prefixes.pop();
+        let key = self.outer_keys.pop().flatten();
+        self.set_key(key);
         Ok(())
     }
 }
 
-impl<'a, W> ::serde::ser::SerializeStruct for &'a mut Serializer<W>
+impl<W> ::serde::ser::SerializeStruct for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -372,17 +1731,25 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        self.current_key = Some(String::from(key));
-        value.serialize(&mut **self)
+        let key = self.field_key(key);
+        self.set_key(Some(key.clone()));
+        self.serialize_ordered(key, value, false)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        Serializer::skip_field(self, key);
+        Ok(())
     }
 
     fn end(self) -> Result<()> {
-        self.current_key = None;
+        self.end_entries(false)?;
+        let key = self.outer_keys.pop().flatten();
+        self.set_key(key);
         Ok(())
     }
 }
 
-impl<'a, W> ::serde::ser::SerializeStructVariant for &'a mut Serializer<W>
+impl<W> ::serde::ser::SerializeStructVariant for &mut Serializer<W>
 where
     W: io::Write,
 {
@@ -393,12 +1760,20 @@ where
     where
         T: ?Sized + ::serde::ser::Serialize,
     {
-        self.current_key = Some(String::from(key));
-        value.serialize(&mut **self)
+        let key = self.field_key(key);
+        self.set_key(Some(key.clone()));
+        self.serialize_ordered(key, value, false)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<()> {
+        Serializer::skip_field(self, key);
+        Ok(())
     }
 
     fn end(self) -> Result<()> {
-        self.current_key = None;
+        self.end_entries(false)?;
+        let key = self.outer_keys.pop().flatten();
+        self.set_key(key);
         Ok(())
     }
 }
@@ -416,7 +1791,7 @@ impl From<StringOnlySerializer> for String {
     }
 }
 
-impl<'a> ::serde::ser::Serializer for &'a mut StringOnlySerializer {
+impl ::serde::ser::Serializer for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -434,43 +1809,63 @@ impl<'a> ::serde::ser::Serializer for &'a mut StringOnlySerializer {
     }
 
     #[inline]
-    fn serialize_i8(self, _value: i8) -> Result<()> {
-        Err(Self::Error::unsupported("i8"))
+    fn serialize_i8(self, value: i8) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_i16(self, value: i16) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_i32(self, value: i32) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
-    fn serialize_i16(self, _value: i16) -> Result<()> {
-        Err(Self::Error::unsupported("i16"))
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
-    fn serialize_i32(self, _value: i32) -> Result<()> {
-        Err(Self::Error::unsupported("i32"))
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
-    fn serialize_i64(self, _value: i64) -> Result<()> {
-        Err(Self::Error::unsupported("i64"))
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
-    fn serialize_u8(self, _value: u8) -> Result<()> {
-        Err(Self::Error::unsupported("u8"))
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
-    fn serialize_u16(self, _value: u16) -> Result<()> {
-        Err(Self::Error::unsupported("u16"))
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
-    fn serialize_u32(self, _value: u32) -> Result<()> {
-        Err(Self::Error::unsupported("u32"))
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
-    fn serialize_u64(self, _value: u64) -> Result<()> {
-        Err(Self::Error::unsupported("u64"))
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        self.value = value.to_string();
+        Ok(())
     }
 
     #[inline]
@@ -528,9 +1923,10 @@ impl<'a> ::serde::ser::Serializer for &'a mut StringOnlySerializer {
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<()> {
-        Err(Self::Error::unsupported("unit variant"))
+        self.value = String::from(variant);
+        Ok(())
     }
 
     #[inline]
@@ -607,7 +2003,7 @@ impl<'a> ::serde::ser::Serializer for &'a mut StringOnlySerializer {
     }
 }
 
-impl<'a> ::serde::ser::SerializeSeq for &'a mut StringOnlySerializer {
+impl ::serde::ser::SerializeSeq for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -623,7 +2019,7 @@ impl<'a> ::serde::ser::SerializeSeq for &'a mut StringOnlySerializer {
     }
 }
 
-impl<'a> ::serde::ser::SerializeTuple for &'a mut StringOnlySerializer {
+impl ::serde::ser::SerializeTuple for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -639,7 +2035,7 @@ impl<'a> ::serde::ser::SerializeTuple for &'a mut StringOnlySerializer {
     }
 }
 
-impl<'a> ::serde::ser::SerializeTupleStruct for &'a mut StringOnlySerializer {
+impl ::serde::ser::SerializeTupleStruct for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -655,7 +2051,7 @@ impl<'a> ::serde::ser::SerializeTupleStruct for &'a mut StringOnlySerializer {
     }
 }
 
-impl<'a> ::serde::ser::SerializeTupleVariant for &'a mut StringOnlySerializer {
+impl ::serde::ser::SerializeTupleVariant for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -671,7 +2067,7 @@ impl<'a> ::serde::ser::SerializeTupleVariant for &'a mut StringOnlySerializer {
     }
 }
 
-impl<'a> ::serde::ser::SerializeMap for &'a mut StringOnlySerializer {
+impl ::serde::ser::SerializeMap for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -694,7 +2090,7 @@ impl<'a> ::serde::ser::SerializeMap for &'a mut StringOnlySerializer {
     }
 }
 
-impl<'a> ::serde::ser::SerializeStruct for &'a mut StringOnlySerializer {
+impl ::serde::ser::SerializeStruct for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -710,7 +2106,7 @@ impl<'a> ::serde::ser::SerializeStruct for &'a mut StringOnlySerializer {
     }
 }
 
-impl<'a> ::serde::ser::SerializeStructVariant for &'a mut StringOnlySerializer {
+impl ::serde::ser::SerializeStructVariant for &mut StringOnlySerializer {
     type Ok = ();
     type Error = Error;
 
@@ -726,25 +2122,82 @@ impl<'a> ::serde::ser::SerializeStructVariant for &'a mut StringOnlySerializer {
     }
 }
 
+/// Serialize a map key, i.e. a string, char, integer or unit variant, into a
+/// string without encoding it.
+pub(crate) fn to_key_string<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut string_serializer = StringOnlySerializer::default();
+    value.serialize(&mut string_serializer)?;
+    Ok(string_serializer.into())
+}
+
+/// Serialize a single value under the given key with the default
+/// configuration.
+pub(crate) fn to_vec_keyed<T>(key: &str, value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut ser = Serializer::new(Vec::new());
+    ser.set_key(Some(String::from(key)));
+    value.serialize(&mut ser)?;
+    Ok(ser.writer.inner)
+}
+
+/// Encodes the bytes as standard Base64 with padding.
+fn encode_base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(char::from(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Encodes the bytes as lowercase hexadecimal.
+fn encode_hex(bytes: &[u8]) -> String {
+    use fmt::Write;
+    let mut encoded = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(encoded, "{:02x}", byte);
+    }
+    encoded
+}
+
 /// Serialize the given data structure as URL parameters into the IO stream.
 ///
+/// The writer is flushed before returning.
+///
 /// # Errors
 ///
 /// Serialization fails if:
 ///
 /// * `T`'s implementation of `Serialize` decides to fail,
-/// * `T` is a type without keys, i.e. not a struct.
+/// * `T` is a type without keys, i.e. neither a struct nor a map,
 /// * `T` contains a nested struct,
-/// * `T` contains a map.
+/// * `T` contains a map whose keys are not scalars, e.g. tuples.
 #[inline]
-pub fn to_writer<W, T: ?Sized>(writer: W, value: &T) -> Result<()>
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
 where
     W: io::Write,
-    T: ::serde::ser::Serialize,
+    T: ?Sized + ::serde::ser::Serialize,
 {
     let mut ser = Serializer::new(writer);
     value.serialize(&mut ser)?;
-    Ok(())
+    ser.finish()
 }
 
 /// Serialize the given data structure as a byte vector containing URL
@@ -755,19 +2208,209 @@ where
 /// Serialization fails if:
 ///
 /// * `T`'s implementation of `Serialize` decides to fail,
-/// * `T` is a type without keys, i.e. not a struct.
+/// * `T` is a type without keys, i.e. neither a struct nor a map,
 /// * `T` contains a nested struct,
-/// * `T` contains a map.
+/// * `T` contains a map whose keys are not scalars, e.g. tuples.
 #[inline]
-pub fn to_vec<T: ?Sized>(value: &T) -> Result<Vec<u8>>
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
 where
-    T: ::serde::ser::Serialize,
+    T: ?Sized + ::serde::ser::Serialize,
 {
     let mut writer = Vec::with_capacity(128);
     to_writer(&mut writer, value)?;
     Ok(writer)
 }
 
+/// Serialize the given data structure into key value pairs, which are not
+/// percent-encoded.
+///
+/// The pairs are the parameters which [`to_string`](fn.to_string.html) would
+/// write, in the same order, but with decoded keys and values. A parameter
+/// without value, e.g. a flag, has an empty value. This is useful to pass the
+/// parameters to an HTTP client which encodes them itself. See
+/// [`to_encoded_pairs`] for pairs encoded as in the output.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+///     tag: Vec<&'static str>,
+/// }
+///
+/// let search = Search {
+///     q: "a b",
+///     tag: vec!["x&y"],
+/// };
+/// assert_eq!(
+///     serde_url_params::to_pairs(&search).unwrap(),
+///     [
+///         (String::from("q"), String::from("a b")),
+///         (String::from("tag"), String::from("x&y")),
+///     ]
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html). Further, [`Raw`] fragments and
+/// [`PreEncoded`] values are not supported, since they cannot be decoded.
+///
+/// [`Raw`]: ../helpers/struct.Raw.html
+/// [`PreEncoded`]: ../helpers/struct.PreEncoded.html
+pub fn to_pairs<T>(value: &T) -> Result<Vec<(String, String)>>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    Config::default().to_pairs(value)
+}
+
+/// Serialize the given data structure into key value pairs, which are
+/// percent-encoded exactly as in the output.
+///
+/// Joining the pairs with `=` and `&` gives the output of
+/// [`to_string`](fn.to_string.html), except that a parameter without value,
+/// e.g. a flag, has an empty value, i.e. is written as `key=`. Unlike
+/// [`to_pairs`], [`PreEncoded`] values are supported.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+///     tag: Vec<&'static str>,
+/// }
+///
+/// let search = Search {
+///     q: "a b",
+///     tag: vec!["x&y"],
+/// };
+/// assert_eq!(
+///     serde_url_params::to_encoded_pairs(&search).unwrap(),
+///     [
+///         (String::from("q"), String::from("a+b")),
+///         (String::from("tag"), String::from("x%26y")),
+///     ]
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html). Further, [`Raw`] fragments are
+/// not supported, since they are not split into pairs.
+///
+/// [`Raw`]: ../helpers/struct.Raw.html
+/// [`PreEncoded`]: ../helpers/struct.PreEncoded.html
+pub fn to_encoded_pairs<T>(value: &T) -> Result<Vec<(String, String)>>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    Config::default().to_encoded_pairs(value)
+}
+
+/// Serialize the given data structure into a map from each key to its
+/// values, which are not percent-encoded.
+///
+/// The values of a repeated key are grouped in output order, and the keys are
+/// sorted. Like [`to_pairs`], a parameter without value has an empty value.
+/// This is useful for assertions in tests, or for structured access to the
+/// parameters.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+///     tag: Vec<&'static str>,
+/// }
+///
+/// let search = Search {
+///     q: "a b",
+///     tag: vec!["x", "y"],
+/// };
+/// let map = serde_url_params::to_map(&search).unwrap();
+/// assert_eq!(map["q"], ["a b"]);
+/// assert_eq!(map["tag"], ["x", "y"]);
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_pairs`].
+pub fn to_map<T>(value: &T) -> Result<BTreeMap<String, Vec<String>>>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    Config::default().to_map(value)
+}
+
+/// Serialize the given data structure into key value pairs using the given
+/// configuration, either decoded or encoded as in the output.
+pub(crate) fn to_pairs_config<T>(
+    value: &T,
+    config: Config,
+    encoded: bool,
+) -> Result<Vec<(String, String)>>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut ser = Serializer::new_with_config(io::sink(), config);
+    ser.pairs = Some(Vec::new());
+    ser.encoded_pairs = encoded;
+    ser.prepare(value)?;
+    value.serialize(&mut ser)?;
+    ser.finish()?;
+    Ok(ser.pairs.take().unwrap_or_default())
+}
+
+/// Serialize the given data structure as a String of URL parameters, encoded
+/// by `url::form_urlencoded::Serializer`.
+///
+/// The value is serialized into key value pairs like by
+/// [`to_string`](fn.to_string.html), which are then passed to the form
+/// serializer of the `url` crate. This guarantees byte-for-byte the same
+/// encoding as the `url` crate, e.g. also for keys. Unlike with
+/// [`to_string`](fn.to_string.html), a parameter without value, e.g. a flag
+/// of [`BoolFormat::FlagIfTrue`] or [`UnitFormat::Flag`] with
+/// [`Config::to_form_urlencoded`], is written with an empty value as `key=`,
+/// since form pairs always have a value.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Form {
+///     #[serde(rename = "first name")]
+///     first_name: &'static str,
+///     tag: Vec<&'static str>,
+/// }
+///
+/// let form = Form {
+///     first_name: "Jane",
+///     tag: vec!["a&b", "c"],
+/// };
+/// assert_eq!(
+///     serde_url_params::to_form_urlencoded(&form).unwrap(),
+///     "first+name=Jane&tag=a%26b&tag=c"
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html). Further, [`Raw`] fragments and
+/// [`PreEncoded`] values are not supported, since they are already encoded.
+///
+/// [`Raw`]: ../helpers/struct.Raw.html
+/// [`PreEncoded`]: ../helpers/struct.PreEncoded.html
+pub fn to_form_urlencoded<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    Config::default().to_form_urlencoded(value)
+}
+
 /// Serialize the given data structure as a String of URL parameters.
 ///
 /// # Errors
@@ -775,15 +2418,347 @@ where
 /// Serialization fails if:
 ///
 /// * `T`'s implementation of `Serialize` decides to fail,
-/// * `T` is a type without keys, i.e. not a struct.
+/// * `T` is a type without keys, i.e. neither a struct nor a map,
 /// * `T` contains a nested struct,
-/// * `T` contains a map.
+/// * `T` contains a map whose keys are not scalars, e.g. tuples.
 #[inline]
-pub fn to_string<T: ?Sized>(value: &T) -> Result<String>
+pub fn to_string<T>(value: &T) -> Result<String>
 where
-    T: ::serde::ser::Serialize,
+    T: ?Sized + ::serde::ser::Serialize,
 {
     let vec = to_vec(value)?;
     let string = String::from_utf8(vec)?;
     Ok(string)
 }
+
+/// Serialize the given data structure as a String of URL parameters, if the
+/// predicate holds for it.
+///
+/// Returns `None` without serializing if the predicate fails. This is meant
+/// for conditional request building, e.g. to send a query only if any filter
+/// is set.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Filter {
+///     tag: Vec<&'static str>,
+///     author: Option<&'static str>,
+/// }
+///
+/// let is_set = |filter: &Filter| !filter.tag.is_empty() || filter.author.is_some();
+/// let filter = Filter {
+///     tag: vec![],
+///     author: None,
+/// };
+/// assert_eq!(serde_url_params::to_string_if(&filter, is_set).unwrap(), None);
+/// let filter = Filter {
+///     tag: vec!["rust"],
+///     author: None,
+/// };
+/// assert_eq!(
+///     serde_url_params::to_string_if(&filter, is_set).unwrap(),
+///     Some(String::from("tag=rust"))
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html).
+pub fn to_string_if<T, F>(value: &T, predicate: F) -> Result<Option<String>>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+    F: FnOnce(&T) -> bool,
+{
+    if !predicate(value) {
+        return Ok(None);
+    }
+    to_string(value).map(Some)
+}
+
+/// Returns the length in bytes of the URL parameters the given data structure
+/// serializes to, without building the output.
+///
+/// This is useful e.g. for deciding whether the parameters fit into the query
+/// of a URL, or have to be sent in a request body instead.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+///     page: u32,
+/// }
+///
+/// let search = Search { q: "a b", page: 2 };
+/// assert_eq!(serde_url_params::serialized_len(&search).unwrap(), 12);
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html).
+pub fn serialized_len<T>(value: &T) -> Result<usize>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    serialized_len_config(value, Config::default())
+}
+
+pub(crate) fn serialized_len_config<T>(value: &T, config: Config) -> Result<usize>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut ser = Serializer::new_with_config(io::sink(), config);
+    ser.prepare(value)?;
+    value.serialize(&mut ser)?;
+    ser.finish()?;
+    Ok(ser.writer.count)
+}
+
+/// Serialize the given data structure as a String of URL parameters together
+/// with the byte range of each emitted parameter in the output.
+///
+/// Each span covers the `key=value` pair (resp. the bare key) without the
+/// separating `&`. This is intended for tooling, e.g. for highlighting which
+/// field produced which part of a query string, and is only available with the
+/// `unstable` feature.
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html).
+#[cfg(feature = "unstable")]
+pub fn to_string_with_spans<T>(value: &T) -> Result<(String, Vec<Span>)>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut ser = Serializer::new(Vec::with_capacity(128));
+    ser.spans = Some(Vec::new());
+    value.serialize(&mut ser)?;
+    ser.finish()?;
+    let spans = ser.spans.unwrap_or_default();
+    let string = String::from_utf8(ser.writer.inner)?;
+    Ok((string, spans))
+}
+
+/// Serialize the given data structure as a String of URL parameters, and
+/// report all values which were dropped on the way.
+///
+/// ```rust
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+///     page: Option<u32>,
+///     filter: Vec<&'static str>,
+/// }
+///
+/// let search = Search {
+///     q: "rust",
+///     page: None,
+///     filter: vec![],
+/// };
+/// let (query, warnings) = serde_url_params::to_string_with_report(&search).unwrap();
+/// assert_eq!(query, "q=rust");
+/// assert_eq!(warnings[0].to_string(), "dropped none value `page`");
+/// assert_eq!(warnings[1].to_string(), "dropped empty sequence `filter`");
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html).
+pub fn to_string_with_report<T>(value: &T) -> Result<(String, Vec<Warning>)>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    Config::default().to_string_with_report(value)
+}
+
+/// Serialize the given data structure as a String of URL parameters, and
+/// explain for each top level struct field or map entry whether it was
+/// emitted or why not.
+///
+/// Unlike [`to_string_with_report`], this also lists the emitted fields and
+/// the fields skipped by `#[serde(skip_serializing_if = "...")]`, which never
+/// reach the serializer otherwise. Note that a struct with a
+/// `#[serde(flatten)]` field is serialized as a map, so its skipped fields are
+/// not listed.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::{FieldOutcome, Warning};
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+///     page: Option<u32>,
+///     #[serde(skip_serializing_if = "Vec::is_empty")]
+///     filter: Vec<&'static str>,
+/// }
+///
+/// let search = Search {
+///     q: "rust",
+///     page: None,
+///     filter: vec![],
+/// };
+/// let (query, explanations) = serde_url_params::to_string_explained(&search).unwrap();
+/// assert_eq!(query, "q=rust");
+/// assert_eq!(
+///     explanations,
+///     [
+///         (String::from("q"), FieldOutcome::Emitted),
+///         (
+///             String::from("page"),
+///             FieldOutcome::Dropped(Warning::DroppedNone(String::from("page")))
+///         ),
+///         (String::from("filter"), FieldOutcome::SkippedByPredicate),
+///     ]
+/// );
+/// ```
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html).
+pub fn to_string_explained<T>(value: &T) -> Result<(String, Vec<Explanation>)>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    Config::default().to_string_explained(value)
+}
+
+/// Serialize the given data structure into a String of URL parameters
+/// together with the outcome of each top level field, using the given
+/// configuration.
+pub(crate) fn to_string_explained_config<T>(
+    value: &T,
+    config: Config,
+) -> Result<(String, Vec<Explanation>)>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut ser = Serializer::new_with_config(Vec::with_capacity(128), config);
+    ser.warnings = Some(Vec::new());
+    ser.explanations = Some(Vec::new());
+    ser.prepare(value)?;
+    value.serialize(&mut ser)?;
+    ser.finish()?;
+    let explanations = ser.explanations.take().unwrap_or_default();
+    let string = String::from_utf8(ser.writer.inner)?;
+    Ok((string, explanations))
+}
+
+/// Serialize the given data structure into a String of URL parameters
+/// together with the dropped values, using the given configuration.
+pub(crate) fn to_string_with_report_config<T>(
+    value: &T,
+    config: Config,
+) -> Result<(String, Vec<Warning>)>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut ser = Serializer::new_with_config(Vec::with_capacity(128), config);
+    ser.warnings = Some(Vec::new());
+    ser.prepare(value)?;
+    value.serialize(&mut ser)?;
+    ser.finish()?;
+    let warnings = ser.warnings.take().unwrap_or_default();
+    let string = String::from_utf8(ser.writer.inner)?;
+    Ok((string, warnings))
+}
+
+/// Serialize the given data structure as URL parameters into the asynchronous
+/// IO stream.
+///
+/// Since Serde serialization is synchronous, the parameters are serialized
+/// into an in-memory buffer first, which is then written to the stream as a
+/// whole. The writer is flushed before returning. This function is only
+/// available with the `tokio` feature.
+///
+/// # Errors
+///
+/// Same as [`to_writer`](fn.to_writer.html).
+#[cfg(feature = "tokio")]
+pub async fn to_async_writer<W, T>(mut writer: W, value: &T) -> Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    use tokio::io::AsyncWriteExt;
+    let buf = to_vec(value)?;
+    writer.write_all(&buf).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Adapter writing the output of the serializer into a `fmt::Write`, e.g. a
+/// small string buffer.
+///
+/// The serializer writes whole UTF-8 strings, so each written chunk is valid
+/// UTF-8 by itself.
+#[cfg(any(feature = "compact_str", feature = "smol_str"))]
+struct FmtWriter<F>(F);
+
+#[cfg(any(feature = "compact_str", feature = "smol_str"))]
+impl<F: fmt::Write> io::Write for FmtWriter<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s = std::str::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.0.write_str(s).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Serialize the given data structure as URL parameters into the
+/// `fmt::Write` string buffer.
+#[cfg(any(feature = "compact_str", feature = "smol_str"))]
+fn to_fmt_writer<F, T>(writer: F, value: &T) -> Result<F>
+where
+    F: fmt::Write,
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    let mut ser = Serializer::new(FmtWriter(writer));
+    value.serialize(&mut ser)?;
+    ser.finish()?;
+    Ok(ser.into_inner().0)
+}
+
+/// Serialize the given data structure as a `CompactString` of URL
+/// parameters.
+///
+/// The parameters are written into the `CompactString` directly, so short
+/// queries are kept inline without a heap allocation. This function is only
+/// available with the `compact_str` feature.
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html).
+#[cfg(feature = "compact_str")]
+pub fn to_compact_string<T>(value: &T) -> Result<compact_str::CompactString>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    to_fmt_writer(compact_str::CompactString::default(), value)
+}
+
+/// Serialize the given data structure as a `SmolStr` of URL parameters.
+///
+/// The parameters are written into a `SmolStrBuilder` directly, so short
+/// queries are kept inline without a heap allocation. This function is only
+/// available with the `smol_str` feature.
+///
+/// # Errors
+///
+/// Same as [`to_string`](fn.to_string.html).
+#[cfg(feature = "smol_str")]
+pub fn to_smolstr<T>(value: &T) -> Result<smol_str::SmolStr>
+where
+    T: ?Sized + ::serde::ser::Serialize,
+{
+    to_fmt_writer(smol_str::SmolStrBuilder::new(), value).map(|builder| builder.finish())
+}
@@ -0,0 +1,205 @@
+//! Build URL parameters incrementally from several values.
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::ser::Serializer;
+use serde::Serialize;
+use std::io;
+
+/// Writer appending the parameters of several values to one query.
+///
+/// Each appended value, typically a struct, is serialized right away into the
+/// underlying writer, separated by `&` from the parameters written before.
+/// This allows to build a query across several function calls.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use serde_url_params::QueryWriter;
+///
+/// #[derive(Serialize)]
+/// struct Search {
+///     q: &'static str,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Page {
+///     page: u32,
+///     per_page: Option<u32>,
+/// }
+///
+/// let mut writer = QueryWriter::new(Vec::new());
+/// writer.append(&Search { q: "rust" }).unwrap();
+/// writer.append(&Page { page: 2, per_page: None }).unwrap();
+/// assert_eq!(writer.finish().unwrap(), b"q=rust&page=2");
+/// ```
+///
+/// [`KeyCollision::LastWins`] applies within each appended value and to the
+/// extra parameters. Keys written by values appended before are kept.
+///
+/// [`KeyCollision::LastWins`]: ../config/enum.KeyCollision.html#variant.LastWins
+pub struct QueryWriter<W> {
+    ser: Serializer<W>,
+}
+
+impl<W> QueryWriter<W>
+where
+    W: io::Write,
+{
+    /// Creates a writer writing into `writer` with the default configuration.
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Config::default())
+    }
+
+    /// Creates a writer writing into `writer` with the given configuration.
+    pub fn with_config(writer: W, config: Config) -> Self {
+        QueryWriter {
+            ser: Serializer::new_with_config(writer, config),
+        }
+    }
+
+    /// Serializes the parameters of `value` and appends them to the query.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`to_writer`](../ser/fn.to_writer.html). Parameters written
+    /// before the error remain in the underlying writer, while parameters
+    /// still buffered, e.g. to be reordered, are discarded. Values can be
+    /// appended after an error as usual.
+    pub fn append<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let res = self
+            .ser
+            .prepare(value)
+            .and_then(|()| value.serialize(&mut self.ser));
+        if res.is_err() {
+            self.ser.reset();
+        }
+        res
+    }
+
+    /// Returns whether no parameter was appended so far.
+    ///
+    /// Parameters buffered until [`finish`](#method.finish), e.g. to coalesce
+    /// duplicate keys, count as appended.
+    pub fn is_empty(&self) -> bool {
+        !self.ser.has_params()
+    }
+
+    /// Finishes the query, e.g. by appending the configured extra parameters,
+    /// and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        self.ser.finish()?;
+        Ok(self.ser.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryWriter;
+    use crate::{ArrayFormat, Config, KeyCollision, Order};
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Serialize)]
+    struct Filter {
+        tags: Vec<&'static str>,
+    }
+
+    #[test]
+    fn test_append() {
+        let mut writer = QueryWriter::new(Vec::new());
+        assert!(writer.is_empty());
+        writer.append(&Filter { tags: vec![] }).unwrap();
+        assert!(writer.is_empty());
+        writer
+            .append(&Filter {
+                tags: vec!["a", "b"],
+            })
+            .unwrap();
+        assert!(!writer.is_empty());
+        let mut extra = BTreeMap::new();
+        extra.insert("page", 2);
+        writer.append(&extra).unwrap();
+        assert!(writer.append(&42).is_err());
+        assert_eq!(writer.finish().unwrap(), b"tags=a&tags=b&page=2");
+    }
+
+    #[test]
+    fn test_append_after_error() {
+        #[derive(Debug, Serialize)]
+        struct Inner {
+            a: u32,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct Bad {
+            page: u32,
+            inner: Inner,
+        }
+
+        let bad = Bad {
+            page: 2,
+            inner: Inner { a: 1 },
+        };
+        let mut writer = QueryWriter::new(Vec::new());
+        writer.append(&Filter { tags: vec!["a"] }).unwrap();
+        assert!(writer.append(&bad).is_err());
+        assert!(writer.append(&42).is_err());
+        writer.append(&Filter { tags: vec!["b"] }).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"tags=a&page=2&tags=b");
+
+        // parameters buffered for reordering are discarded
+        let config = Config::new().order(Order::Reverse);
+        let mut writer = QueryWriter::with_config(Vec::new(), config.clone());
+        assert!(writer.append(&bad).is_err());
+        assert!(writer.is_empty());
+        writer.append(&Filter { tags: vec!["b"] }).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"tags=b");
+
+        let mut writer = QueryWriter::with_config(Vec::new(), config);
+        writer.append(&Filter { tags: vec!["a"] }).unwrap();
+        assert!(writer.append(&bad).is_err());
+        writer.append(&Filter { tags: vec!["b"] }).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"tags=a&tags=b");
+    }
+
+    #[test]
+    fn test_append_with_config() {
+        let config = Config::new()
+            .array_format(ArrayFormat::Indexed)
+            .extra_params(&[("v", "1")])
+            .trailing_separator(true);
+        let mut writer = QueryWriter::with_config(Vec::new(), config.clone());
+        writer.append(&Filter { tags: vec!["a"] }).unwrap();
+        writer.append(&Filter { tags: vec!["b"] }).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"tags[0]=a&tags[0]=b&v=1&");
+
+        let writer = QueryWriter::with_config(Vec::new(), config);
+        assert_eq!(writer.finish().unwrap(), b"v=1&");
+
+        let config = Config::new().coalesce_duplicate_keys(true);
+        let mut writer = QueryWriter::with_config(Vec::new(), config);
+        assert!(writer.is_empty());
+        writer.append(&Filter { tags: vec!["a"] }).unwrap();
+        assert!(!writer.is_empty());
+        writer.append(&Filter { tags: vec!["b"] }).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"tags=a,b");
+
+        #[derive(Debug, Serialize)]
+        struct Collide {
+            a: u32,
+            #[serde(flatten)]
+            extra: BTreeMap<&'static str, u32>,
+        }
+        let mut extra = BTreeMap::new();
+        extra.insert("a", 2);
+        let params = Collide { a: 1, extra };
+        let config = Config::new().key_collision(KeyCollision::LastWins);
+        assert_eq!(config.to_string(&params).unwrap(), "a=2");
+        let mut writer = QueryWriter::with_config(Vec::new(), config);
+        writer.append(&params).unwrap();
+        assert_eq!(writer.finish().unwrap(), b"a=2");
+    }
+}